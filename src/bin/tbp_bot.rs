@@ -0,0 +1,187 @@
+//! A small reference bot for the Tetris Bot Protocol.
+//!
+//! It drops the current piece straight down in every orientation and column
+//! and rates the resulting boards with a few classic heuristics (aggregate
+//! height, holes, bumpiness and cleared lines). Run the game with
+//! `cargo run -- --bot target/debug/tbp_bot` to watch it play.
+
+#[path = "../game/heuristic.rs"]
+mod heuristic;
+#[path = "../game/tbp/protocol.rs"]
+mod protocol;
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, Write},
+};
+
+use heuristic::rate_board;
+use protocol::{
+    BotMessage, FrontendMessage, Move, Orientation, PieceLocation, Spin, TbpPiece, BOARD_HEIGHT,
+    BOARD_WIDTH,
+};
+
+type Board = Vec<[bool; BOARD_WIDTH]>;
+
+#[derive(Default)]
+struct Bot {
+    board: Board,
+    queue: VecDeque<TbpPiece>,
+}
+
+fn main() -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+
+    send(
+        &mut stdout,
+        &BotMessage::Info {
+            name: "bevy-tetris reference bot".into(),
+            version: env!("CARGO_PKG_VERSION").into(),
+            author: "bevy-tetris".into(),
+            features: vec![],
+        },
+    )?;
+
+    let mut bot = Bot::default();
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let message = match serde_json::from_str::<FrontendMessage>(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("Ignoring message {line:?}: {e}");
+                continue;
+            }
+        };
+
+        match message {
+            FrontendMessage::Rules => send(&mut stdout, &BotMessage::Ready)?,
+            FrontendMessage::Start(start) => {
+                bot.board = start
+                    .board
+                    .iter()
+                    .map(|row| {
+                        let mut cells = [false; BOARD_WIDTH];
+                        cells
+                            .iter_mut()
+                            .zip(row)
+                            .for_each(|(cell, value)| *cell = value.is_some());
+                        cells
+                    })
+                    .collect();
+                bot.board.resize(BOARD_HEIGHT, [false; BOARD_WIDTH]);
+                bot.queue = start.queue.into();
+            }
+            FrontendMessage::Stop => bot = Bot::default(),
+            FrontendMessage::Suggest => {
+                let moves = bot.suggest();
+                send(&mut stdout, &BotMessage::Suggestion { moves })?;
+            }
+            FrontendMessage::Play { mv } => {
+                place(&mut bot.board, &mv.location);
+                clear_lines(&mut bot.board);
+                bot.queue.pop_front();
+            }
+            FrontendMessage::NewPiece { piece } => bot.queue.push_back(piece),
+            FrontendMessage::Quit => break,
+        }
+    }
+
+    Ok(())
+}
+
+fn send(stdout: &mut impl Write, message: &BotMessage) -> io::Result<()> {
+    serde_json::to_writer(&mut *stdout, message)?;
+    writeln!(stdout)?;
+    stdout.flush()
+}
+
+impl Bot {
+    /// All reachable drops of the current piece, best first.
+    fn suggest(&self) -> Vec<Move> {
+        let Some(&piece) = self.queue.front() else {
+            return vec![];
+        };
+
+        let mut rated: Vec<_> = [
+            Orientation::North,
+            Orientation::East,
+            Orientation::South,
+            Orientation::West,
+        ]
+        .into_iter()
+        .flat_map(|orientation| (-2..BOARD_WIDTH as i32 + 2).map(move |x| (orientation, x)))
+        .filter_map(|(orientation, x)| {
+            let location = self.drop_location(PieceLocation {
+                piece,
+                orientation,
+                x,
+                y: BOARD_HEIGHT as i32 - 3,
+            })?;
+
+            let mut board = self.board.clone();
+            place(&mut board, &location);
+            Some((rate(&mut board), location))
+        })
+        .collect();
+
+        rated.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        rated
+            .into_iter()
+            .map(|(_, location)| Move {
+                location,
+                spin: Spin::None,
+            })
+            .collect()
+    }
+
+    /// Moves the piece down from its start location until it lands.
+    fn drop_location(&self, mut location: PieceLocation) -> Option<PieceLocation> {
+        if !fits(&self.board, &location) {
+            return None;
+        }
+        loop {
+            let below = PieceLocation {
+                y: location.y - 1,
+                ..location
+            };
+            if !fits(&self.board, &below) {
+                return Some(location);
+            }
+            location = below;
+        }
+    }
+}
+
+fn fits(board: &Board, location: &PieceLocation) -> bool {
+    location.cells().iter().all(|&(x, y)| {
+        (0..BOARD_WIDTH as i32).contains(&x)
+            && (0..BOARD_HEIGHT as i32).contains(&y)
+            && !board[y as usize][x as usize]
+    })
+}
+
+fn place(board: &mut Board, location: &PieceLocation) {
+    for (x, y) in location.cells() {
+        if let Some(cell) = board
+            .get_mut(y as usize)
+            .and_then(|row| row.get_mut(x as usize))
+        {
+            *cell = true;
+        }
+    }
+}
+
+fn clear_lines(board: &mut Board) -> usize {
+    board.retain(|row| !row.iter().all(|cell| *cell));
+    let cleared = BOARD_HEIGHT - board.len();
+    board.resize(BOARD_HEIGHT, [false; BOARD_WIDTH]);
+    cleared
+}
+
+/// Clears full rows and rates the board, higher is better.
+fn rate(board: &mut Board) -> f32 {
+    let cleared = clear_lines(board);
+    rate_board(BOARD_WIDTH, BOARD_HEIGHT, cleared, |x, y| board[y][x])
+}
@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+
+use super::{
+    clock::{format_time, GameClock},
+    GameSummary, Score,
+};
+
+/// A board that plays with pieces of 2×2 minos.
+#[derive(Component, Debug)]
+pub struct Big;
+
+/// Sums up the game before the board is torn down.
+pub(super) fn summarize_big(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    big_query: Query<&Score, With<Big>>,
+) {
+    for score in big_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: format!("Reached level {}", score.level()),
+            rows: vec![
+                ("Lines", score.lines.to_string()),
+                ("Pieces", score.pieces.to_string()),
+                ("Time", format_time(clock.elapsed)),
+            ],
+        });
+    }
+}
@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::*;
+
+use super::{
+    piece_order::{PieceOrder, Randomizer},
+    playfield::{ClearRule, Playfield},
+    Piece, Score, StepTimer,
+};
+
+/// One independent game. The playfield, piece queue, gravity timer and score
+/// are components of the board entity, the falling [`super::Piece`] is a child.
+#[derive(Reflect, Component, Debug)]
+pub struct Board {
+    pub index: usize,
+}
+
+/// Marks a board that could not spawn a new piece.
+#[derive(Component, Debug)]
+pub struct ToppedOut;
+
+/// Gravity keeps the speed it started with instead of following the level.
+#[derive(Component, Debug)]
+pub struct FixedGravity;
+
+/// A piece on the ground locks once this runs out instead of with the next
+/// gravity step. Moving or rotating the piece starts it over.
+#[derive(Component, Debug)]
+pub struct LockDelay(pub Timer);
+
+/// Whether boards are played mirrored left to right, chosen in the menu.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MirrorMode(pub bool);
+
+/// Time until the next piece enters the board, also known as ARE.
+#[derive(Component, Debug)]
+pub struct SpawnDelay(pub Timer);
+
+/// Keyboard bindings of a board controlled by a human player.
+#[derive(Component, Debug, Clone)]
+pub struct Controls {
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub soft_drop: KeyCode,
+    pub hard_drop: KeyCode,
+    pub rotate_ccw: KeyCode,
+    pub rotate_cw: KeyCode,
+    /// Only used on boards that can [`super::hold::Hold`] a piece.
+    pub hold: KeyCode,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+            soft_drop: KeyCode::Down,
+            hard_drop: KeyCode::Space,
+            rotate_ccw: KeyCode::Up,
+            rotate_cw: KeyCode::X,
+            hold: KeyCode::C,
+        }
+    }
+}
+
+impl Controls {
+    /// The keys as they act on `piece`. A mirrored board is shown flipped
+    /// back, so on it left and right swap.
+    pub fn for_piece(&self, piece: &Piece) -> Self {
+        if piece.mirrored {
+            Self {
+                left: self.right,
+                right: self.left,
+                ..self.clone()
+            }
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Left side of a shared keyboard.
+    pub fn wasd() -> Self {
+        Self {
+            left: KeyCode::A,
+            right: KeyCode::D,
+            soft_drop: KeyCode::S,
+            hard_drop: KeyCode::W,
+            rotate_ccw: KeyCode::Q,
+            rotate_cw: KeyCode::E,
+            hold: KeyCode::ShiftLeft,
+        }
+    }
+
+    /// Right side of a shared keyboard.
+    pub fn arrows_and_numpad() -> Self {
+        Self {
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+            soft_drop: KeyCode::Down,
+            hard_drop: KeyCode::Up,
+            rotate_ccw: KeyCode::Numpad1,
+            rotate_cw: KeyCode::Numpad3,
+            hold: KeyCode::Numpad0,
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct BoardBundle {
+    name: Name,
+    board: Board,
+    playfield: Playfield,
+    piece_order: PieceOrder,
+    step_timer: StepTimer,
+    score: Score,
+    rng: EntropyComponent<ChaCha8Rng>,
+}
+
+impl BoardBundle {
+    pub fn new(index: usize, size: UVec2, mut rng: EntropyComponent<ChaCha8Rng>) -> Self {
+        let score = Score::default();
+        Self {
+            name: Name::new(format!("Player {}", index + 1)),
+            board: Board { index },
+            playfield: Playfield::new(size),
+            piece_order: PieceOrder::new(&mut rng),
+            step_timer: StepTimer(Timer::from_seconds(score.speed(), TimerMode::Repeating)),
+            score,
+            rng,
+        }
+    }
+
+    pub fn with_name(self, name: String) -> Self {
+        Self {
+            name: Name::new(name),
+            ..self
+        }
+    }
+
+    /// Starts the board at a higher level with faster gravity.
+    pub fn with_start_level(self, start_level: u32) -> Self {
+        let score = Score {
+            start_level,
+            ..self.score
+        };
+        Self {
+            step_timer: StepTimer(Timer::from_seconds(score.speed(), TimerMode::Repeating)),
+            score,
+            ..self
+        }
+    }
+
+    pub fn with_randomizer(mut self, randomizer: Randomizer) -> Self {
+        self.piece_order = PieceOrder::with_randomizer(randomizer, &mut self.rng);
+        self
+    }
+
+    /// Starts with these cells, keeping the clear rule of the board. On a
+    /// mirrored board the cells are mirrored too.
+    pub fn with_playfield(self, mut playfield: Playfield) -> Self {
+        playfield.set_clear_rule(self.playfield.clear_rule());
+        playfield.set_mirrored(self.playfield.is_mirrored());
+        Self { playfield, ..self }
+    }
+
+    pub fn with_clear_rule(mut self, clear_rule: ClearRule) -> Self {
+        self.playfield.set_clear_rule(clear_rule);
+        self
+    }
+
+    pub fn with_mirrored(mut self, mirrored: bool) -> Self {
+        self.playfield.set_mirrored(mirrored);
+        self
+    }
+
+    pub fn with_piece_order(self, piece_order: PieceOrder) -> Self {
+        Self {
+            piece_order,
+            ..self
+        }
+    }
+
+    pub fn with_gravity(self, step: Duration) -> Self {
+        Self {
+            step_timer: StepTimer(Timer::new(step, TimerMode::Repeating)),
+            ..self
+        }
+    }
+}
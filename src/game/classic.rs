@@ -0,0 +1,210 @@
+//! A preset that plays like NES Tetris: no wall kicks, no hard drop, one next
+//! piece, the NES randomizer, gravity, scoring and delayed auto shift.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::{
+    board::Controls,
+    clock::{format_time, GameClock},
+    piece_order::PieceOrder,
+    piece_types::{piece_letter, PieceType},
+    playfield::Playfield,
+    rotation::Rotation,
+    GameSummary, Piece, PieceLocked, Score, StepTimer,
+};
+
+/// Highest level a classic game can start at.
+pub const MAX_CLASSIC_START_LEVEL: u32 = 19;
+
+/// Level a classic game starts at, chosen in the menu.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClassicStartLevel(pub u32);
+
+/// The NES runs at slightly more than 60 frames per second.
+const FRAME: Duration = Duration::from_nanos(16_639_267);
+
+/// Frames until a held direction starts repeating.
+const DAS_DELAY: u32 = 16;
+
+/// Frames between two repeated moves.
+const DAS_REPEAT: u32 = 6;
+
+/// Points for clearing one to four lines, multiplied by the level plus one.
+const LINE_POINTS: [u32; 5] = [0, 40, 100, 300, 1200];
+
+/// Frames per row of gravity from a level on.
+const GRAVITY: [(u32, u32); 15] = [
+    (0, 48),
+    (1, 43),
+    (2, 38),
+    (3, 33),
+    (4, 28),
+    (5, 23),
+    (6, 18),
+    (7, 13),
+    (8, 8),
+    (9, 6),
+    (10, 5),
+    (13, 4),
+    (16, 3),
+    (19, 2),
+    (29, 1),
+];
+
+/// Time per row of gravity at `level`.
+pub fn classic_gravity(level: u32) -> Duration {
+    let frames = GRAVITY
+        .iter()
+        .rev()
+        .find(|(from, _)| *from <= level)
+        .map_or(48, |(_, frames)| *frames);
+    FRAME * frames
+}
+
+/// I, S and Z pieces only have two orientations with Nintendo rotation.
+pub fn nintendo_rotation(piece_type: PieceType, rotation: Rotation) -> Rotation {
+    match (piece_type, rotation) {
+        (PieceType::I | PieceType::S | PieceType::Z, Rotation::R180) => Rotation::R0,
+        (PieceType::I | PieceType::S | PieceType::Z, Rotation::R270) => Rotation::R90,
+        _ => rotation,
+    }
+}
+
+/// T, J and L pieces spawn with their flat side up.
+pub fn nintendo_spawn_rotation(piece_type: PieceType) -> Rotation {
+    match piece_type {
+        PieceType::T | PieceType::J | PieceType::L => Rotation::R180,
+        _ => Rotation::R0,
+    }
+}
+
+/// A board playing by the classic rules.
+#[derive(Component, Debug)]
+pub struct Classic {
+    start_level: u32,
+    points: u32,
+    /// How long the current direction has been held.
+    shift_charge: Duration,
+}
+
+impl Classic {
+    pub fn new(ClassicStartLevel(start_level): ClassicStartLevel) -> Self {
+        Self {
+            start_level,
+            points: 0,
+            shift_charge: Duration::ZERO,
+        }
+    }
+
+    /// The first level up takes longer the higher the start level, after that
+    /// the level rises every 10 lines.
+    fn level(&self, lines: u32) -> u32 {
+        let first =
+            (self.start_level * 10 + 10).min((self.start_level * 10).saturating_sub(50).max(100));
+        if lines < first {
+            self.start_level
+        } else {
+            self.start_level + 1 + (lines - first) / 10
+        }
+    }
+}
+
+/// Repeats sideways moves while a direction is held, after the first move
+/// on the press itself.
+pub(super) fn classic_auto_shift(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mut piece_query: Query<(&mut Piece, &Parent)>,
+    mut classic_query: Query<(&mut Classic, &Playfield, &Controls)>,
+) {
+    for (mut piece, parent) in piece_query.iter_mut() {
+        let Ok((mut classic, playfield, controls)) = classic_query.get_mut(parent.get()) else {
+            continue;
+        };
+        let controls = controls.for_piece(&piece);
+
+        let direction = if keys.pressed(controls.left) {
+            IVec2::NEG_X
+        } else if keys.pressed(controls.right) {
+            IVec2::X
+        } else {
+            classic.shift_charge = Duration::ZERO;
+            continue;
+        };
+        if keys.just_pressed(controls.left) || keys.just_pressed(controls.right) {
+            classic.shift_charge = Duration::ZERO;
+            continue;
+        }
+
+        classic.shift_charge += time.delta();
+        while classic.shift_charge >= FRAME * DAS_DELAY {
+            classic.shift_charge -= FRAME * DAS_REPEAT;
+            let moved = Piece {
+                position: piece.position + direction,
+                ..*piece
+            };
+            if playfield.check_move(&moved) {
+                *piece = Piece {
+                    rotated_last: false,
+                    ..moved
+                };
+            }
+        }
+    }
+}
+
+/// Scores clears with the NES table and speeds up gravity on level ups.
+pub(super) fn track_classic(
+    mut locked_events: EventReader<PieceLocked>,
+    mut classic_query: Query<(&mut Classic, &Score, &mut StepTimer)>,
+) {
+    for locked in locked_events.read() {
+        let Ok((mut classic, score, mut timer)) = classic_query.get_mut(locked.board) else {
+            continue;
+        };
+        let lines = locked.cleared_rows as u32;
+        let level = classic.level(score.lines - lines);
+        classic.points += LINE_POINTS[locked.cleared_rows.min(4)] * (level + 1);
+        timer
+            .0
+            .set_duration(classic_gravity(classic.level(score.lines)));
+    }
+}
+
+pub(super) fn classic_hud(
+    mut contexts: EguiContexts,
+    classic_query: Query<(&Classic, &Score, &PieceOrder)>,
+) {
+    for (classic, score, piece_order) in classic_query.iter() {
+        egui::Window::new("Classic").show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("Score: {:06}", classic.points));
+            ui.label(format!("Level: {:02}", classic.level(score.lines)));
+            ui.label(format!("Lines: {:03}", score.lines));
+            if let Some(next) = piece_order.upcoming().first() {
+                ui.label(format!("Next: {}", piece_letter(*next)));
+            }
+        });
+    }
+}
+
+/// Sums up the game before the board is torn down.
+pub(super) fn summarize_classic(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    classic_query: Query<(&Classic, &Score)>,
+) {
+    for (classic, score) in classic_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: format!("Score {:06}", classic.points),
+            rows: vec![
+                ("Level", classic.level(score.lines).to_string()),
+                ("Lines", score.lines.to_string()),
+                ("Start level", classic.start_level.to_string()),
+                ("Time", format_time(clock.elapsed)),
+            ],
+        });
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// Time played in the current game. Unlike the gravity [`super::StepTimer`]
+/// it never resets, so modes can use it for goals and records.
+#[derive(Resource, Debug, Default)]
+pub struct GameClock {
+    pub elapsed: Duration,
+    /// Timed modes end when the clock reaches the limit.
+    pub limit: Option<Duration>,
+}
+
+impl GameClock {
+    pub fn with_limit(limit: Duration) -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            limit: Some(limit),
+        }
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.limit.map(|limit| limit.saturating_sub(self.elapsed))
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.remaining()
+            .is_some_and(|remaining| remaining.is_zero())
+    }
+}
+
+pub(super) fn tick_clock(time: Res<Time>, mut clock: ResMut<GameClock>) {
+    clock.elapsed += time.delta();
+    if let Some(limit) = clock.limit {
+        clock.elapsed = clock.elapsed.min(limit);
+    }
+}
+
+/// Formats a duration as `m:ss.mmm`.
+pub fn format_time(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
@@ -0,0 +1,104 @@
+//! Practice for long combos in a four wide well. The columns beside the well
+//! are garbage that grows back as rows clear, so the well never runs out.
+
+use std::ops::Range;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::{
+    clock::{format_time, GameClock},
+    playfield::{Cell, Playfield},
+    GameSummary, PieceLocked, Score,
+};
+
+/// The columns of the well, the pieces spawn right above them.
+const WELL: Range<usize> = 3..7;
+
+/// Rows of wall, the rows above stay free for the spawning pieces.
+const WALL_HEIGHT: usize = 20;
+
+/// Cells in the bottom of the well at the start, the usual three residue
+/// that leaves room for every piece.
+const RESIDUE: [IVec2; 3] = [IVec2::new(3, 0), IVec2::new(4, 0), IVec2::new(5, 0)];
+
+/// A board practising combos.
+#[derive(Component, Debug, Default)]
+pub struct ComboTraining {
+    /// The combo after the last lock.
+    combo: u32,
+    longest: u32,
+    /// Combos that ended with a lock that cleared nothing.
+    drops: u32,
+    /// Length of all dropped combos together.
+    dropped_length: u32,
+}
+
+impl ComboTraining {
+    fn average(&self) -> f32 {
+        self.dropped_length as f32 / self.drops.max(1) as f32
+    }
+}
+
+/// The playfield a training starts with.
+pub fn combo_playfield(size: UVec2) -> Playfield {
+    let mut playfield = Playfield::new(size);
+    playfield.fill_walls(WELL, WALL_HEIGHT);
+    for cell in RESIDUE {
+        playfield.set_cell(cell, Cell::Garbage);
+    }
+    playfield
+}
+
+/// Counts the combos and grows the walls back after every lock.
+pub(super) fn track_combo(
+    mut locked_events: EventReader<PieceLocked>,
+    mut combo_query: Query<(&mut ComboTraining, &mut Playfield)>,
+) {
+    for locked in locked_events.read() {
+        let Ok((mut training, mut playfield)) = combo_query.get_mut(locked.board) else {
+            continue;
+        };
+        if locked.combo == 0 && training.combo > 0 {
+            training.drops += 1;
+            training.dropped_length += training.combo;
+        }
+        training.combo = locked.combo;
+        training.longest = training.longest.max(locked.combo);
+
+        if locked.cleared_rows > 0 {
+            playfield.fill_walls(WELL, WALL_HEIGHT);
+        }
+    }
+}
+
+pub(super) fn combo_hud(mut contexts: EguiContexts, combo_query: Query<&ComboTraining>) {
+    for training in combo_query.iter() {
+        egui::Window::new("Combo").show(contexts.ctx_mut(), |ui| {
+            ui.heading(training.combo.to_string());
+            ui.label(format!("Longest: {}", training.longest));
+            ui.label(format!("Drops: {}", training.drops));
+            ui.label(format!("Average: {:.1}", training.average()));
+        });
+    }
+}
+
+/// Sums up the game before the board is torn down.
+pub(super) fn summarize_combo(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    combo_query: Query<(&ComboTraining, &Score)>,
+) {
+    for (training, score) in combo_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: format!("Longest combo: {}", training.longest),
+            rows: vec![
+                ("Drops", training.drops.to_string()),
+                ("Average combo", format!("{:.1}", training.average())),
+                ("Lines", score.lines.to_string()),
+                ("Pieces", score.pieces.to_string()),
+                ("Time", format_time(clock.elapsed)),
+            ],
+        });
+    }
+}
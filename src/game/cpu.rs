@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use bevy_prng::ChaCha8Rng;
+use rand_core::RngCore;
+
+use super::{
+    heuristic::rate_board,
+    lock_piece,
+    playfield::{Cell, CheckRotationResult, Playfield},
+    Piece, PieceLocked, Score,
+};
+
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    fn pieces_per_second(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Medium => 1.5,
+            Difficulty::Hard => 3.0,
+        }
+    }
+
+    /// How much the rating of a placement is randomly off. Weaker opponents
+    /// misjudge more and so pick worse placements.
+    fn misjudgement(self) -> f32 {
+        match self {
+            Difficulty::Easy => 2.0,
+            Difficulty::Medium => 0.6,
+            Difficulty::Hard => 0.0,
+        }
+    }
+}
+
+/// A board played by the computer.
+#[derive(Component, Debug)]
+pub struct CpuPlayer {
+    difficulty: Difficulty,
+    move_timer: Timer,
+    /// Misjudgements have their own generator, so they do not change the
+    /// pieces of the board.
+    rng: ChaCha8Rng,
+}
+
+impl CpuPlayer {
+    pub fn new(difficulty: Difficulty, rng: ChaCha8Rng) -> Self {
+        Self {
+            difficulty,
+            move_timer: Timer::from_seconds(
+                1.0 / difficulty.pieces_per_second(),
+                TimerMode::Repeating,
+            ),
+            rng,
+        }
+    }
+}
+
+pub(super) fn play_cpu_moves(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut locked_events: EventWriter<PieceLocked>,
+    mut piece_query: Query<(Entity, &mut Piece, &Parent)>,
+    mut board_query: Query<(&mut Playfield, &mut Score, &mut CpuPlayer)>,
+) {
+    for (entity, mut piece, parent) in piece_query.iter_mut() {
+        let board = parent.get();
+        let Ok((mut playfield, mut score, mut cpu)) = board_query.get_mut(board) else {
+            continue;
+        };
+        if !cpu.move_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        let misjudgement = cpu.difficulty.misjudgement();
+        let rng = &mut cpu.rng;
+        let Some(target) = reachable_placements(&playfield, &piece)
+            .into_iter()
+            .map(|target| {
+                let noise = rng.next_u32() as f32 / u32::MAX as f32 - 0.5;
+                (
+                    rate(&playfield, &target) + 2.0 * noise * misjudgement,
+                    target,
+                )
+            })
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, target)| target)
+        else {
+            continue;
+        };
+
+        *piece = target;
+        lock_piece(
+            &mut commands,
+            &mut locked_events,
+            board,
+            entity,
+            &piece,
+            &mut playfield,
+            &mut score,
+        );
+    }
+}
+
+/// All resting places the piece can get to by rotating where it is, moving
+/// sideways and then dropping straight down.
+fn reachable_placements(playfield: &Playfield, piece: &Piece) -> Vec<Piece> {
+    let mut rotated = *piece;
+    let mut placements = vec![];
+
+    for _ in 0..4 {
+        for step in [IVec2::NEG_X, IVec2::X] {
+            let mut shifted = rotated;
+            while playfield.check_move(&shifted) {
+                let mut dropped = shifted;
+                while playfield.check_move(&Piece {
+                    position: dropped.position + IVec2::NEG_Y * piece.scale,
+                    ..dropped
+                }) {
+                    dropped.position += IVec2::NEG_Y * piece.scale;
+                }
+                placements.push(dropped);
+                shifted.position += step * piece.scale;
+            }
+        }
+
+        let next = Piece {
+            rotation: rotated.rotation.ccw(),
+            ..rotated
+        };
+        match playfield.check_rotation(&next) {
+            CheckRotationResult::ValidWithOffset(offset) => {
+                rotated = Piece {
+                    position: next.position + offset,
+                    ..next
+                }
+            }
+            CheckRotationResult::Invalid => break,
+        }
+    }
+
+    placements
+}
+
+/// Rates the board after locking the piece, higher is better.
+fn rate(playfield: &Playfield, piece: &Piece) -> f32 {
+    let mut playfield = playfield.clone();
+    playfield.set_cells(piece);
+    let cleared_rows = playfield.clear_rows();
+
+    let size = playfield.size();
+    rate_board(size.x as usize, size.y as usize, cleared_rows, |x, y| {
+        !matches!(
+            playfield.get(IVec2::new(x as i32, y as i32)),
+            Some(Cell::Empty)
+        )
+    })
+}
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_prng::ChaCha8Rng;
+use rand_core::SeedableRng;
+
+use crate::setup::GameState;
+
+use super::{
+    board::ToppedOut,
+    clock::{format_time, GameClock},
+    playfield::{GarbagePattern, Playfield},
+    GameSummary, Piece, Score,
+};
+
+/// Most garbage rows in the playfield at once, longer races refill it as
+/// the player digs.
+const DIG_VISIBLE_ROWS: u32 = 10;
+
+/// Time between two rising garbage rows.
+const DIG_RISE_INTERVAL: Duration = Duration::from_secs(8);
+
+const DIG_GARBAGE: GarbagePattern = GarbagePattern::Messy { change_chance: 0.5 };
+
+/// How the next dig race is played, chosen in the menu.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct DigSettings {
+    /// Garbage rows to clear.
+    pub rows: u32,
+    /// Whether new garbage rises while digging.
+    pub rising: bool,
+    /// Seed of the garbage and the pieces, a new one every race if unset.
+    pub seed: Option<u64>,
+}
+
+impl DigSettings {
+    pub const ROWS: [u32; 3] = [10, 18, 100];
+}
+
+impl Default for DigSettings {
+    fn default() -> Self {
+        Self {
+            rows: 10,
+            rising: false,
+            seed: None,
+        }
+    }
+}
+
+/// A board racing to clear all of its garbage.
+#[derive(Component, Debug)]
+pub struct DigRace {
+    seed: u64,
+    /// Garbage rows to clear, including the ones that rose.
+    total: u32,
+    /// Garbage rows of the start that are not in the playfield yet.
+    remaining: u32,
+    rise_timer: Option<Timer>,
+    /// Rows that rose and are pushed in with the next lock.
+    risen: u32,
+    /// Garbage has its own generator, so the pieces played do not change it.
+    rng: ChaCha8Rng,
+}
+
+impl DigRace {
+    pub fn new(settings: DigSettings, seed: u64) -> Self {
+        Self {
+            seed,
+            total: settings.rows,
+            remaining: settings.rows,
+            rise_timer: settings
+                .rising
+                .then(|| Timer::new(DIG_RISE_INTERVAL, TimerMode::Repeating)),
+            risen: 0,
+            rng: ChaCha8Rng::seed_from_u64(seed.wrapping_add(1)),
+        }
+    }
+}
+
+/// Fills the playfield up with garbage and lets new garbage rise. Rows are
+/// only added between two pieces, so they never push into a falling piece.
+pub(super) fn add_dig_garbage(
+    mut commands: Commands,
+    time: Res<Time>,
+    piece_query: Query<&Parent, With<Piece>>,
+    mut dig_query: Query<(Entity, &mut Playfield, &mut DigRace), Without<ToppedOut>>,
+) {
+    for (board, mut playfield, mut race) in dig_query.iter_mut() {
+        if let Some(timer) = &mut race.rise_timer {
+            let rows = timer.tick(time.delta()).times_finished_this_tick();
+            race.risen += rows;
+            race.total += rows;
+        }
+        if piece_query.iter().any(|parent| parent.get() == board) {
+            continue;
+        }
+
+        let room = DIG_VISIBLE_ROWS.saturating_sub(playfield.garbage_rows() as u32);
+        let refill = race.remaining.min(room);
+        let rows = refill + race.risen;
+        if rows == 0 {
+            continue;
+        }
+        race.remaining -= refill;
+        race.risen = 0;
+
+        if playfield.add_garbage(rows as usize, DIG_GARBAGE, &mut race.rng) {
+            commands.entity(board).insert(ToppedOut);
+        }
+    }
+}
+
+pub(super) fn track_dig(mut commands: Commands, dig_query: Query<(&Score, &DigRace)>) {
+    for (score, race) in dig_query.iter() {
+        if score.garbage_lines >= race.total {
+            commands.insert_resource(NextState(Some(GameState::Finished)));
+        }
+    }
+}
+
+pub(super) fn dig_hud(
+    mut contexts: EguiContexts,
+    clock: Res<GameClock>,
+    dig_query: Query<(&Score, &DigRace)>,
+) {
+    for (score, race) in dig_query.iter() {
+        egui::Window::new("Dig Race").show(contexts.ctx_mut(), |ui| {
+            ui.heading(format_time(clock.elapsed));
+            ui.label(format!(
+                "Garbage left: {}",
+                race.total.saturating_sub(score.garbage_lines)
+            ));
+            ui.label(format!("Pieces: {}", score.pieces));
+            ui.label(format!("Seed: {}", race.seed));
+        });
+    }
+}
+
+/// Sums up the race before the board is torn down, however it ended.
+pub(super) fn summarize_dig(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    dig_query: Query<(&Score, &DigRace)>,
+) {
+    for (score, race) in dig_query.iter() {
+        let heading = if score.garbage_lines >= race.total {
+            "Dug through!".to_string()
+        } else {
+            format!("{} garbage rows left", race.total - score.garbage_lines)
+        };
+        commands.insert_resource(GameSummary {
+            heading,
+            rows: vec![
+                ("Time", format_time(clock.elapsed)),
+                ("Garbage lines", score.garbage_lines.to_string()),
+                ("Lines", score.lines.to_string()),
+                ("Pieces", score.pieces.to_string()),
+                ("Seed", race.seed.to_string()),
+            ],
+        });
+    }
+}
@@ -0,0 +1,36 @@
+//! The board rating of the built-in CPU, shared with the reference bot in
+//! `src/bin/tbp_bot.rs`, so it must not depend on anything else.
+
+/// Rates a board after a placement and its line clears with a few classic
+/// heuristics (aggregate height, holes, bumpiness and cleared lines), higher
+/// is better. `filled` tells whether the cell at `x`, `y` is filled, rows
+/// count from the bottom.
+pub fn rate_board(
+    width: usize,
+    height: usize,
+    cleared_rows: usize,
+    filled: impl Fn(usize, usize) -> bool,
+) -> f32 {
+    let heights: Vec<usize> = (0..width)
+        .map(|x| {
+            (0..height)
+                .rev()
+                .find(|&y| filled(x, y))
+                .map_or(0, |y| y + 1)
+        })
+        .collect();
+
+    let holes: usize = (0..width)
+        .map(|x| (0..heights[x]).filter(|&y| !filled(x, y)).count())
+        .sum();
+
+    let aggregate_height: usize = heights.iter().sum();
+    let bumpiness: usize = heights
+        .windows(2)
+        .map(|pair| pair[0].abs_diff(pair[1]))
+        .sum();
+
+    -0.51 * aggregate_height as f32 + 0.76 * cleared_rows as f32
+        - 0.36 * holes as f32
+        - 0.18 * bumpiness as f32
+}
@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+
+use super::{
+    board::{Controls, ToppedOut},
+    piece_types::PieceType,
+    playfield::Playfield,
+    zen::{Zen, ZEN_CLEARED_ROWS},
+    Piece, PieceLocked,
+};
+
+/// Lets a board put the falling piece aside and take it out again later.
+#[derive(Component, Debug, Default, Clone)]
+pub struct Hold {
+    pub piece: Option<PieceType>,
+    /// A piece can only be held once, until the next one locks.
+    used: bool,
+}
+
+impl Hold {
+    pub fn new(piece: Option<PieceType>) -> Self {
+        Self { piece, used: false }
+    }
+}
+
+/// Swaps the falling piece with the held one. Without a held piece the next
+/// piece of the queue is spawned instead.
+pub(super) fn hold_piece(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut locked_events: EventReader<PieceLocked>,
+    piece_query: Query<(Entity, &Piece, &Parent)>,
+    mut hold_query: Query<(&mut Hold, &Controls, &mut Playfield, Has<Zen>)>,
+) {
+    let mut locked_boards = vec![];
+    for locked in locked_events.read() {
+        if let Ok((mut hold, ..)) = hold_query.get_mut(locked.board) {
+            hold.used = false;
+        }
+        locked_boards.push(locked.board);
+    }
+
+    for (entity, piece, parent) in piece_query.iter() {
+        let board = parent.get();
+        // the piece of a board that just locked is already gone
+        if locked_boards.contains(&board) {
+            continue;
+        }
+        let Ok((mut hold, controls, mut playfield, zen)) = hold_query.get_mut(board) else {
+            continue;
+        };
+        if hold.used || !keys.just_pressed(controls.hold) {
+            continue;
+        }
+
+        hold.used = true;
+        commands.entity(entity).despawn_recursive();
+        if let Some(held) = hold.piece.replace(piece.piece_type) {
+            // the held piece comes back at the top like a new one, and may
+            // not fit there either
+            let held_piece = Piece::new(held).spawn(&playfield);
+            if zen && !playfield.check_move(&held_piece) {
+                playfield.clear_top_rows(ZEN_CLEARED_ROWS);
+            }
+            if playfield.check_move(&held_piece) {
+                commands.entity(board).with_children(|cb| {
+                    cb.spawn((Name::new("Current Piece"), held_piece));
+                });
+            } else {
+                commands.entity(board).insert(ToppedOut);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::{
+    board::ToppedOut,
+    clock::{format_time, GameClock},
+    GameSummary, PieceLocked, Score,
+};
+
+/// Time a fading block takes to disappear.
+const FADE_TIME: Duration = Duration::from_secs(4);
+
+/// Time the stack shows after a line clear.
+const CLEAR_REVEAL: Duration = Duration::from_millis(600);
+
+/// Time the stack shows before the game is over.
+const GAME_OVER_REVEAL: Duration = Duration::from_secs(3);
+
+/// How the stack hides in the invisible mode, chosen in the menu.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HiddenStack {
+    /// Blocks fade out over a few seconds after they lock.
+    #[default]
+    Fading,
+    /// Blocks disappear as soon as they lock.
+    Invisible,
+}
+
+impl HiddenStack {
+    pub const ALL: [HiddenStack; 2] = [HiddenStack::Fading, HiddenStack::Invisible];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HiddenStack::Fading => "Fading",
+            HiddenStack::Invisible => "Invisible",
+        }
+    }
+}
+
+/// A board whose locked blocks cannot be seen.
+#[derive(Component, Debug)]
+pub struct Invisible {
+    hidden: HiddenStack,
+    /// Time the stack is still shown for.
+    revealed: Duration,
+}
+
+impl Invisible {
+    pub fn new(hidden: HiddenStack) -> Self {
+        Self {
+            hidden,
+            revealed: Duration::ZERO,
+        }
+    }
+
+    pub fn is_revealed(&self) -> bool {
+        !self.revealed.is_zero()
+    }
+
+    /// How visible a block is that was filled the given time ago, from 0 to 1.
+    pub fn visibility(&self, since_filled: Duration) -> f32 {
+        if self.is_revealed() {
+            return 1.0;
+        }
+        match self.hidden {
+            HiddenStack::Fading => 1.0 - (since_filled.as_secs_f32() / FADE_TIME.as_secs_f32()),
+            HiddenStack::Invisible => 0.0,
+        }
+        .clamp(0.0, 1.0)
+    }
+}
+
+/// Shows the stack for a moment after line clears, and before the game
+/// ends when the board tops out.
+pub(super) fn reveal_stack(
+    time: Res<Time>,
+    mut locked_events: EventReader<PieceLocked>,
+    mut invisible_query: Query<(&mut Invisible, Option<Ref<ToppedOut>>)>,
+) {
+    for (mut invisible, topped_out) in invisible_query.iter_mut() {
+        if topped_out.is_some_and(|topped_out| topped_out.is_added()) {
+            invisible.revealed = GAME_OVER_REVEAL;
+        } else {
+            invisible.revealed = invisible.revealed.saturating_sub(time.delta());
+        }
+    }
+    for locked in locked_events.read() {
+        if locked.cleared_rows == 0 {
+            continue;
+        }
+        if let Ok((mut invisible, _)) = invisible_query.get_mut(locked.board) {
+            invisible.revealed = invisible.revealed.max(CLEAR_REVEAL);
+        }
+    }
+}
+
+/// Sums up the game before the board is torn down.
+pub(super) fn summarize_invisible(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    invisible_query: Query<(&Score, &Invisible)>,
+) {
+    for (score, invisible) in invisible_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: format!("{} stack", invisible.hidden.label()),
+            rows: vec![
+                ("Lines", score.lines.to_string()),
+                ("Level", score.level().to_string()),
+                ("Pieces", score.pieces.to_string()),
+                ("Time", format_time(clock.elapsed)),
+            ],
+        });
+    }
+}
@@ -0,0 +1,144 @@
+//! A mode where pieces sometimes carry an item on one of their minos. The
+//! item goes off when its line is cleared.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use rand_core::RngCore;
+
+use super::{
+    board::FixedGravity,
+    clock::{format_time, GameClock},
+    playfield::Playfield,
+    GameSummary, Score, StepTimer,
+};
+
+/// One in this many pieces carries an item.
+const ITEM_CHANCE: u32 = 6;
+
+/// How long slow gravity lasts.
+const SLOW_TIME: Duration = Duration::from_secs(10);
+
+/// Time per row of gravity while it is slowed down.
+const SLOW_GRAVITY: Duration = Duration::from_secs(2);
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Item {
+    /// Clears the 3×3 area around it.
+    Bomb,
+    /// Clears its column.
+    Laser,
+    /// Mirrors the stack left to right.
+    Flip,
+    /// Slows the gravity down for a while.
+    SlowGravity,
+}
+
+impl Item {
+    const ALL: [Item; 4] = [Item::Bomb, Item::Laser, Item::Flip, Item::SlowGravity];
+
+    /// Items use the tiles of the atlas after the ones of the pieces.
+    pub fn sprite(self) -> TextureAtlasSprite {
+        let (color, index) = match self {
+            Item::Bomb => (Color::CRIMSON, 8),
+            Item::Laser => (Color::CYAN, 9),
+            Item::Flip => (Color::FUCHSIA, 10),
+            Item::SlowGravity => (Color::ALICE_BLUE, 11),
+        };
+
+        TextureAtlasSprite {
+            color,
+            index,
+            ..default()
+        }
+    }
+}
+
+/// Decides whether a new piece carries an item and which one.
+pub fn roll_item(rng: &mut impl RngCore) -> Option<Item> {
+    if !rng.next_u32().is_multiple_of(ITEM_CHANCE) {
+        return None;
+    }
+    Some(Item::ALL[rng.next_u32() as usize % Item::ALL.len()])
+}
+
+/// A board that plays with items.
+#[derive(Component, Debug, Default)]
+pub struct Items {
+    /// Time the gravity stays slowed down for.
+    slowed: Duration,
+    used: u32,
+}
+
+/// Sets off the items of cleared lines and ends slow gravity when it runs
+/// out.
+pub(super) fn activate_items(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut items_query: Query<(Entity, &mut Items, &mut Playfield, &mut StepTimer)>,
+) {
+    for (board, mut items, mut playfield, mut timer) in items_query.iter_mut() {
+        if !items.slowed.is_zero() {
+            items.slowed = items.slowed.saturating_sub(time.delta());
+            if items.slowed.is_zero() {
+                // the usual gravity takes over again with the next step
+                commands.entity(board).remove::<FixedGravity>();
+            }
+        }
+
+        for (position, item) in playfield.take_triggered_items() {
+            items.used += 1;
+            match item {
+                Item::Bomb => {
+                    for y in -1..=1 {
+                        for x in -1..=1 {
+                            playfield.clear_cell(position + IVec2::new(x, y));
+                        }
+                    }
+                }
+                Item::Laser => {
+                    for y in 0..playfield.size().y as i32 {
+                        playfield.clear_cell(IVec2::new(position.x, y));
+                    }
+                }
+                Item::Flip => playfield.flip(),
+                Item::SlowGravity => {
+                    items.slowed = SLOW_TIME;
+                    timer.0.set_duration(SLOW_GRAVITY);
+                    commands.entity(board).insert(FixedGravity);
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn items_hud(mut contexts: EguiContexts, items_query: Query<&Items>) {
+    for items in items_query.iter() {
+        egui::Window::new("Items").show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("Items used: {}", items.used));
+            if !items.slowed.is_zero() {
+                ui.label(format!("Slow gravity: {:.1} s", items.slowed.as_secs_f32()));
+            }
+        });
+    }
+}
+
+/// Sums up the game before the board is torn down.
+pub(super) fn summarize_items(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    items_query: Query<(&Score, &Items)>,
+) {
+    for (score, items) in items_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: format!("Reached level {}", score.level()),
+            rows: vec![
+                ("Lines", score.lines.to_string()),
+                ("Items used", items.used.to_string()),
+                ("Pieces", score.pieces.to_string()),
+                ("Time", format_time(clock.elapsed)),
+            ],
+        });
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use crate::setup::GameState;
+
+use super::{
+    clock::{format_time, GameClock},
+    GameSummary, Score,
+};
+
+/// Lines to clear in a marathon that is not endless.
+pub const MARATHON_LINES: u32 = 150;
+
+/// Highest level a marathon can start at.
+pub const MAX_START_LEVEL: u32 = 15;
+
+/// How the next marathon is played, chosen in the menu.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct MarathonSettings {
+    pub start_level: u32,
+    pub endless: bool,
+}
+
+impl Default for MarathonSettings {
+    fn default() -> Self {
+        Self {
+            start_level: 1,
+            endless: false,
+        }
+    }
+}
+
+/// A single player board that plays until the goal is reached or it tops
+/// out.
+#[derive(Component, Debug)]
+pub struct Marathon {
+    goal: Option<u32>,
+}
+
+impl Marathon {
+    pub fn new(settings: MarathonSettings) -> Self {
+        Self {
+            goal: (!settings.endless).then_some(MARATHON_LINES),
+        }
+    }
+}
+
+pub(super) fn track_marathon(mut commands: Commands, marathon_query: Query<(&Score, &Marathon)>) {
+    for (score, marathon) in marathon_query.iter() {
+        if marathon.goal.is_some_and(|goal| score.lines >= goal) {
+            commands.insert_resource(NextState(Some(GameState::Finished)));
+        }
+    }
+}
+
+/// Sums up the game before the board is torn down, however it ended.
+pub(super) fn summarize_marathon(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    marathon_query: Query<(&Score, &Marathon)>,
+) {
+    for (score, marathon) in marathon_query.iter() {
+        let completed = marathon.goal.is_some_and(|goal| score.lines >= goal);
+        let pieces_per_second = if clock.elapsed.is_zero() {
+            0.0
+        } else {
+            score.pieces as f32 / clock.elapsed.as_secs_f32()
+        };
+
+        commands.insert_resource(GameSummary {
+            heading: if completed {
+                "Marathon complete!".to_string()
+            } else {
+                format!("Reached level {}", score.level())
+            },
+            rows: vec![
+                ("Score", score.score.to_string()),
+                ("Lines", score.lines.to_string()),
+                ("Level", score.level().to_string()),
+                ("Time", format_time(clock.elapsed)),
+                ("Pieces per second", format!("{pieces_per_second:.2}")),
+            ],
+        });
+    }
+}
@@ -0,0 +1,349 @@
+//! Arcade style mode with the level counting, gravity and grading of the
+//! first Tetris: The Grand Master.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::setup::GameState;
+
+use super::{
+    board::SpawnDelay,
+    clock::{format_time, GameClock},
+    lock_piece,
+    playfield::Playfield,
+    GameSummary, Piece, PieceLocked, Score,
+};
+
+/// The game is cleared at this level.
+const MASTER_MAX_LEVEL: u32 = 999;
+
+/// Levels per section, the level stops before each section boundary until
+/// lines are cleared.
+const SECTION_LEVELS: u32 = 100;
+
+/// The timings are given in frames of the arcade machine.
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Gravity from a level on, in 1/256 cells per frame. 5120 is 20G, the
+/// piece lands as soon as it appears.
+const GRAVITY: [(u32, u32); 30] = [
+    (0, 4),
+    (30, 6),
+    (35, 8),
+    (40, 10),
+    (50, 12),
+    (60, 16),
+    (70, 32),
+    (80, 48),
+    (90, 64),
+    (100, 80),
+    (120, 96),
+    (140, 112),
+    (160, 128),
+    (170, 144),
+    (200, 4),
+    (220, 32),
+    (230, 64),
+    (233, 96),
+    (236, 128),
+    (239, 160),
+    (243, 192),
+    (247, 224),
+    (251, 256),
+    (300, 512),
+    (330, 768),
+    (360, 1024),
+    (400, 1280),
+    (420, 1024),
+    (450, 768),
+    (500, 5120),
+];
+
+/// Frames of ARE (the delay before the next piece) and lock delay from a
+/// level on.
+const DELAYS: [(u32, (u32, u32)); 5] = [
+    (0, (30, 30)),
+    (600, (16, 26)),
+    (700, (12, 22)),
+    (800, (6, 18)),
+    (900, (6, 15)),
+];
+
+/// Points needed for each grade.
+const GRADES: [(u32, &str); 18] = [
+    (0, "9"),
+    (400, "8"),
+    (800, "7"),
+    (1400, "6"),
+    (2000, "5"),
+    (3500, "4"),
+    (5500, "3"),
+    (8000, "2"),
+    (12000, "1"),
+    (16000, "S1"),
+    (22000, "S2"),
+    (30000, "S3"),
+    (40000, "S4"),
+    (52000, "S5"),
+    (66000, "S6"),
+    (82000, "S7"),
+    (100000, "S8"),
+    (120000, "S9"),
+];
+
+/// To be awarded Grand Master, these levels have to be reached in time and
+/// with at least the given points.
+const GM_CHECKPOINTS: [(u32, Duration, u32); 3] = [
+    (300, Duration::from_secs(4 * 60 + 15), 12000),
+    (500, Duration::from_secs(7 * 60 + 30), 40000),
+    (MASTER_MAX_LEVEL, Duration::from_secs(13 * 60 + 30), 126000),
+];
+
+const SECTION_NAMES: [&str; 10] = [
+    "000-099", "100-199", "200-299", "300-399", "400-499", "500-599", "600-699", "700-799",
+    "800-899", "900-999",
+];
+
+/// Looks up the last entry of a table that starts at or below `key`.
+fn lookup<T: Copy>(table: &[(u32, T)], key: u32) -> T {
+    table
+        .iter()
+        .rev()
+        .find(|(from, _)| *from <= key)
+        .map(|(_, value)| *value)
+        .expect("tables start at 0")
+}
+
+/// A board with master rules. Gravity, lock delay and ARE are handled here
+/// instead of by the [`super::StepTimer`].
+#[derive(Component, Debug)]
+pub struct Master {
+    level: u32,
+    points: u32,
+    /// Grows with every clear in a row, multiplies the points of a clear.
+    combo: u32,
+    /// Time each completed section took.
+    section_times: Vec<Duration>,
+    /// Whether all checkpoints so far were passed.
+    on_gm_pace: bool,
+    gm_checkpoints: usize,
+    /// Part of a cell the piece has fallen but not moved yet.
+    fall: f32,
+    lock_delay: Duration,
+    /// Lowest row the piece reached, the lock delay resets below it.
+    lowest_row: i32,
+}
+
+impl Default for Master {
+    fn default() -> Self {
+        Self {
+            level: 0,
+            points: 0,
+            combo: 1,
+            section_times: vec![],
+            on_gm_pace: true,
+            gm_checkpoints: 0,
+            fall: 0.0,
+            lock_delay: Duration::ZERO,
+            lowest_row: i32::MAX,
+        }
+    }
+}
+
+impl Master {
+    fn grade(&self) -> &'static str {
+        if self.level >= MASTER_MAX_LEVEL
+            && self.on_gm_pace
+            && self.gm_checkpoints == GM_CHECKPOINTS.len()
+        {
+            "GM"
+        } else {
+            lookup(&GRADES, self.points)
+        }
+    }
+
+    /// ARE and lock delay at the current level.
+    fn delays(&self) -> (Duration, Duration) {
+        let (are, lock) = lookup(&DELAYS, self.level);
+        (FRAME * are, FRAME * lock)
+    }
+
+    /// The level stops at the last level of a section and right before the
+    /// end, only clears move past it.
+    fn at_level_stop(&self) -> bool {
+        self.level % SECTION_LEVELS == SECTION_LEVELS - 1 || self.level == MASTER_MAX_LEVEL - 1
+    }
+
+    /// Raises the level, recording sections and checkpoints that are passed.
+    fn advance(&mut self, levels: u32, elapsed: Duration) {
+        let old_level = self.level;
+        self.level = (self.level + levels).min(MASTER_MAX_LEVEL);
+
+        let mut sections = self.level / SECTION_LEVELS - old_level / SECTION_LEVELS;
+        if self.level == MASTER_MAX_LEVEL {
+            // the last section ends at 999 instead of 1000
+            sections += 1;
+        }
+        for _ in 0..sections {
+            let before: Duration = self.section_times.iter().sum();
+            self.section_times.push(elapsed - before);
+        }
+
+        while let Some((level, time, points)) = GM_CHECKPOINTS.get(self.gm_checkpoints) {
+            if self.level < *level {
+                break;
+            }
+            self.on_gm_pace &= elapsed <= *time && self.points >= *points;
+            self.gm_checkpoints += 1;
+        }
+    }
+}
+
+/// Every new piece counts as a level, unless the level is at a stop.
+pub(super) fn count_master_pieces(
+    new_pieces: Query<(&Piece, &Parent), Added<Piece>>,
+    mut master_query: Query<&mut Master>,
+    clock: Res<GameClock>,
+) {
+    for (piece, parent) in new_pieces.iter() {
+        let Ok(mut master) = master_query.get_mut(parent.get()) else {
+            continue;
+        };
+        if !master.at_level_stop() {
+            master.advance(1, clock.elapsed);
+        }
+        master.fall = 0.0;
+        master.lock_delay = Duration::ZERO;
+        master.lowest_row = piece.position.y;
+    }
+}
+
+/// Moves pieces down by as many cells as the gravity asks for in this frame
+/// and locks them once the lock delay ran out on the ground.
+pub(super) fn master_gravity(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut locked_events: EventWriter<PieceLocked>,
+    mut piece_query: Query<(Entity, &mut Piece, &Parent)>,
+    mut master_query: Query<(&mut Master, &mut Playfield, &mut Score)>,
+) {
+    for (entity, mut piece, parent) in piece_query.iter_mut() {
+        let board = parent.get();
+        let Ok((mut master, mut playfield, mut score)) = master_query.get_mut(board) else {
+            continue;
+        };
+
+        let gravity = lookup(&GRAVITY, master.level) as f32 / 256.0;
+        master.fall += gravity * (time.delta_seconds() / FRAME.as_secs_f32());
+        let below = |piece: &Piece| Piece {
+            position: piece.position - IVec2::Y,
+            ..*piece
+        };
+        while master.fall >= 1.0 && playfield.check_move(&below(&piece)) {
+            piece.position.y -= 1;
+            piece.rotated_last = false;
+            master.fall -= 1.0;
+        }
+
+        if piece.position.y < master.lowest_row {
+            master.lowest_row = piece.position.y;
+            master.lock_delay = Duration::ZERO;
+        }
+        if playfield.check_move(&below(&piece)) {
+            continue;
+        }
+
+        master.fall = 0.0;
+        master.lock_delay += time.delta();
+        if master.lock_delay >= master.delays().1 {
+            lock_piece(
+                &mut commands,
+                &mut locked_events,
+                board,
+                entity,
+                &piece,
+                &mut playfield,
+                &mut score,
+            );
+        }
+    }
+}
+
+/// Scores clears, raises the level and waits for the ARE after every lock.
+pub(super) fn track_master(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut locked_events: EventReader<PieceLocked>,
+    mut master_query: Query<&mut Master>,
+) {
+    for locked in locked_events.read() {
+        let Ok(mut master) = master_query.get_mut(locked.board) else {
+            continue;
+        };
+        let are = master.delays().0;
+        commands
+            .entity(locked.board)
+            .insert(SpawnDelay(Timer::new(are, TimerMode::Once)));
+
+        let lines = locked.cleared_rows as u32;
+        if lines == 0 {
+            master.combo = 1;
+            continue;
+        }
+        master.combo += 2 * lines - 2;
+        let bravo = if locked.perfect_clear { 4 } else { 1 };
+        master.points += (master.level + lines).div_ceil(4) * lines * master.combo * bravo;
+        master.advance(lines, clock.elapsed);
+
+        if master.level >= MASTER_MAX_LEVEL {
+            commands.insert_resource(NextState(Some(GameState::Finished)));
+        }
+    }
+}
+
+pub(super) fn master_hud(
+    mut contexts: EguiContexts,
+    clock: Res<GameClock>,
+    master_query: Query<&Master>,
+) {
+    for master in master_query.iter() {
+        egui::Window::new("Master").show(contexts.ctx_mut(), |ui| {
+            ui.heading(format_time(clock.elapsed));
+            ui.label(format!("Grade: {}", master.grade()));
+            ui.label(format!("Points: {}", master.points));
+            let next_stop = (master.level / SECTION_LEVELS + 1) * SECTION_LEVELS;
+            ui.label(format!(
+                "Level: {:03} / {}",
+                master.level,
+                next_stop.min(MASTER_MAX_LEVEL)
+            ));
+        });
+    }
+}
+
+/// Sums up the game before the board is torn down, however it ended.
+pub(super) fn summarize_master(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    master_query: Query<&Master>,
+) {
+    for master in master_query.iter() {
+        let mut rows = vec![
+            ("Level", master.level.to_string()),
+            ("Points", master.points.to_string()),
+            ("Time", format_time(clock.elapsed)),
+        ];
+        rows.extend(
+            SECTION_NAMES
+                .iter()
+                .zip(&master.section_times)
+                .map(|(name, time)| (*name, format_time(*time))),
+        );
+        commands.insert_resource(GameSummary {
+            heading: format!("Grade {}", master.grade()),
+            rows,
+        });
+    }
+}
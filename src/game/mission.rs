@@ -0,0 +1,235 @@
+//! A campaign of objectives that have to be completed in order, each one
+//! before its time runs out. Later missions ask for more and fall faster.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::setup::GameState;
+
+use super::{
+    clock::{format_time, GameClock},
+    piece_types::{piece_letter, PieceType},
+    playfield::TSpin,
+    GameSummary, PieceLocked, Score,
+};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, Copy)]
+enum Objective {
+    /// Clear exactly `rows` lines at once, `times` times.
+    Clears { rows: usize, times: u32 },
+    /// Clear this many lines in any way.
+    Lines(u32),
+    /// Clear lines by placing this piece.
+    ClearWith(PieceType),
+    /// T-spins that clear at least `rows` lines, mini ones count for none.
+    TSpin { rows: usize, times: u32 },
+    /// Clear lines with this many locks in a row.
+    Combo(u32),
+}
+
+impl Objective {
+    fn label(self) -> String {
+        match self {
+            Objective::Clears { rows, times } => {
+                let clear = match rows {
+                    1 => "single",
+                    2 => "double",
+                    3 => "triple",
+                    _ => "tetris",
+                };
+                match times {
+                    1 => format!("Clear a {clear}"),
+                    _ => format!("Clear {times} {clear}s"),
+                }
+            }
+            Objective::Lines(lines) => format!("Clear {lines} lines"),
+            Objective::ClearWith(piece_type) => {
+                format!("Clear lines with {}", piece_letter(piece_type))
+            }
+            Objective::TSpin { rows, times } => {
+                let spin = match rows {
+                    0 => "T-spin",
+                    1 => "T-spin single",
+                    2 => "T-spin double",
+                    _ => "T-spin triple",
+                };
+                match times {
+                    1 => format!("Do a {spin}"),
+                    _ => format!("Do {times} {spin}s"),
+                }
+            }
+            Objective::Combo(combo) => format!("Make a combo of {combo}"),
+        }
+    }
+
+    /// Progress needed to complete the objective.
+    fn goal(self) -> u32 {
+        match self {
+            Objective::Clears { times, .. } | Objective::TSpin { times, .. } => times,
+            Objective::Lines(lines) => lines,
+            Objective::ClearWith(_) => 1,
+            Objective::Combo(combo) => combo,
+        }
+    }
+
+    /// The progress after a lock.
+    fn advance(self, progress: u32, locked: &PieceLocked) -> u32 {
+        let rows = locked.cleared_rows;
+        let counts = match self {
+            Objective::Clears { rows: wanted, .. } => rows == wanted,
+            Objective::Lines(_) => return progress + rows as u32,
+            Objective::ClearWith(piece_type) => rows > 0 && locked.piece.piece_type == piece_type,
+            Objective::TSpin { rows: wanted, .. } => locked.t_spin == TSpin::Full && rows >= wanted,
+            // counted from the start of the mission, so a combo that was
+            // already running does not carry over, and a lock without a
+            // clear breaks it
+            Objective::Combo(_) if rows > 0 => return progress + 1,
+            Objective::Combo(_) => return 0,
+        };
+        progress + counts as u32
+    }
+}
+
+struct Mission {
+    objective: Objective,
+    time: Duration,
+}
+
+const fn mission(objective: Objective, seconds: u64) -> Mission {
+    Mission {
+        objective,
+        time: Duration::from_secs(seconds),
+    }
+}
+
+const MISSIONS: [Mission; 12] = [
+    mission(Objective::Lines(4), 60),
+    mission(Objective::Clears { rows: 2, times: 2 }, 60),
+    mission(Objective::ClearWith(PieceType::I), 45),
+    mission(Objective::Combo(3), 60),
+    mission(Objective::Clears { rows: 3, times: 1 }, 60),
+    mission(Objective::TSpin { rows: 0, times: 1 }, 75),
+    mission(Objective::ClearWith(PieceType::L), 40),
+    mission(Objective::Clears { rows: 4, times: 1 }, 60),
+    mission(Objective::Clears { rows: 3, times: 2 }, 90),
+    mission(Objective::TSpin { rows: 2, times: 1 }, 90),
+    mission(Objective::Combo(5), 75),
+    mission(Objective::Clears { rows: 4, times: 2 }, 90),
+];
+
+/// A board playing the missions, one after another.
+#[derive(Component, Debug)]
+pub struct Campaign {
+    index: usize,
+    progress: u32,
+    /// When the current mission has to be done, on the game clock.
+    deadline: Duration,
+}
+
+impl Default for Campaign {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            progress: 0,
+            deadline: MISSIONS[0].time,
+        }
+    }
+}
+
+impl Campaign {
+    fn mission(&self) -> Option<&'static Mission> {
+        MISSIONS.get(self.index)
+    }
+}
+
+/// Advances the current mission with every lock and moves on to the next one
+/// once it is complete. Each mission raises the level by one.
+pub(super) fn track_mission(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut locked_events: EventReader<PieceLocked>,
+    mut campaign_query: Query<(&mut Campaign, &mut Score)>,
+) {
+    for locked in locked_events.read() {
+        let Ok((mut campaign, mut score)) = campaign_query.get_mut(locked.board) else {
+            continue;
+        };
+        let Some(mission) = campaign.mission() else {
+            continue;
+        };
+        campaign.progress = mission.objective.advance(campaign.progress, locked);
+        if campaign.progress < mission.objective.goal() {
+            continue;
+        }
+
+        campaign.index += 1;
+        campaign.progress = 0;
+        score.start_level += 1;
+        match campaign.mission() {
+            Some(next) => campaign.deadline = clock.elapsed + next.time,
+            None => commands.insert_resource(NextState(Some(GameState::Finished))),
+        }
+    }
+
+    for campaign in campaign_query.iter().map(|(campaign, _)| campaign) {
+        if campaign.mission().is_some() && clock.elapsed >= campaign.deadline {
+            commands.insert_resource(NextState(Some(GameState::GameOver)));
+        }
+    }
+}
+
+pub(super) fn mission_hud(
+    mut contexts: EguiContexts,
+    clock: Res<GameClock>,
+    campaign_query: Query<&Campaign>,
+) {
+    for campaign in campaign_query.iter() {
+        let Some(mission) = campaign.mission() else {
+            continue;
+        };
+        egui::Window::new("Mission").show(contexts.ctx_mut(), |ui| {
+            ui.heading(format_time(campaign.deadline.saturating_sub(clock.elapsed)));
+            ui.label(format!(
+                "Mission {} / {}",
+                campaign.index + 1,
+                MISSIONS.len()
+            ));
+            ui.label(mission.objective.label());
+            ui.label(format!(
+                "Progress: {} / {}",
+                campaign.progress,
+                mission.objective.goal()
+            ));
+        });
+    }
+}
+
+/// Sums up the game before the board is torn down.
+pub(super) fn summarize_mission(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    campaign_query: Query<(&Campaign, &Score)>,
+) {
+    for (campaign, score) in campaign_query.iter() {
+        let heading = match campaign.mission() {
+            Some(mission) => format!("Failed: {}", mission.objective.label()),
+            None => "All missions complete".to_string(),
+        };
+        commands.insert_resource(GameSummary {
+            heading,
+            rows: vec![
+                (
+                    "Missions",
+                    format!("{} / {}", campaign.index, MISSIONS.len()),
+                ),
+                ("Lines", score.lines.to_string()),
+                ("Time", format_time(clock.elapsed)),
+            ],
+        });
+    }
+}
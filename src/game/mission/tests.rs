@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+
+use super::{Objective, PieceLocked, PieceType, TSpin};
+use crate::game::Piece;
+
+fn locked(piece_type: PieceType, cleared_rows: usize, t_spin: TSpin, combo: u32) -> PieceLocked {
+    PieceLocked {
+        board: Entity::PLACEHOLDER,
+        piece: Piece::new(piece_type),
+        cleared_rows,
+        chain: (cleared_rows > 0) as u32,
+        t_spin,
+        combo,
+        back_to_back: false,
+        perfect_clear: false,
+    }
+}
+
+fn clear(rows: usize) -> PieceLocked {
+    locked(PieceType::O, rows, TSpin::None, (rows > 0) as u32)
+}
+
+#[test]
+fn clears_count_the_wanted_size_only() {
+    let objective = Objective::Clears { rows: 2, times: 2 };
+    assert_eq!(objective.advance(0, &clear(2)), 1);
+    assert_eq!(objective.advance(1, &clear(1)), 1);
+    assert_eq!(objective.advance(1, &clear(4)), 1);
+    assert_eq!(objective.advance(1, &clear(2)), 2);
+}
+
+#[test]
+fn lines_add_up() {
+    let objective = Objective::Lines(4);
+    assert_eq!(objective.advance(0, &clear(0)), 0);
+    assert_eq!(objective.advance(1, &clear(3)), 4);
+}
+
+#[test]
+fn clears_with_a_piece_need_that_piece() {
+    let objective = Objective::ClearWith(PieceType::I);
+    assert_eq!(objective.advance(0, &clear(1)), 0);
+    let with_i = locked(PieceType::I, 1, TSpin::None, 1);
+    assert_eq!(objective.advance(0, &with_i), 1);
+    let no_clear = locked(PieceType::I, 0, TSpin::None, 0);
+    assert_eq!(objective.advance(0, &no_clear), 0);
+}
+
+#[test]
+fn t_spins_need_enough_rows() {
+    let objective = Objective::TSpin { rows: 2, times: 1 };
+    let single = locked(PieceType::T, 1, TSpin::Full, 1);
+    let mini = locked(PieceType::T, 2, TSpin::Mini, 1);
+    let double = locked(PieceType::T, 2, TSpin::Full, 1);
+    assert_eq!(objective.advance(0, &single), 0);
+    assert_eq!(objective.advance(0, &mini), 0);
+    assert_eq!(objective.advance(0, &double), 1);
+}
+
+#[test]
+fn combo_counts_from_the_start_of_the_mission() {
+    let objective = Objective::Combo(3);
+    // the combo was already at 5 when the mission started
+    let running = locked(PieceType::O, 1, TSpin::None, 5);
+    assert_eq!(objective.advance(0, &running), 1);
+    assert_eq!(objective.advance(1, &running), 2);
+    assert_eq!(objective.advance(2, &clear(0)), 0);
+}
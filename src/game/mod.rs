@@ -1,99 +1,570 @@
+mod big;
+mod board;
+mod classic;
+mod clock;
+mod combo;
+mod cpu;
+mod dig;
+mod heuristic;
+mod hold;
+mod invisible;
+mod items;
+mod marathon;
+mod master;
+mod mission;
+mod mode;
+mod net;
+mod perfect_clear;
 mod piece_order;
 mod piece_types;
 mod playfield;
+mod puzzle;
+mod records;
 mod render;
 mod rotation;
+mod sandbox;
+mod scoring;
+mod spectator;
+mod sprint;
+mod survival;
+mod tbp;
+mod ultra;
+mod versus;
+mod zen;
 
 use std::time::Duration;
 
-use bevy::{ecs::query::QuerySingleError, prelude::*};
+use bevy::prelude::*;
 use bevy_prng::ChaCha8Rng;
-use bevy_rand::{prelude::*, resource};
+use bevy_rand::prelude::*;
+use rand_core::{RngCore, SeedableRng};
 
 use bevy_egui::{egui, EguiContexts};
 
 use crate::{game::playfield::CheckRotationResult, setup::GameState};
 
 use self::{
-    piece_order::{create_piece_order, PieceOrder},
+    big::{summarize_big, Big},
+    board::{
+        Board, BoardBundle, Controls, FixedGravity, LockDelay, MirrorMode, SpawnDelay, ToppedOut,
+    },
+    classic::{
+        classic_auto_shift, classic_gravity, classic_hud, nintendo_rotation,
+        nintendo_spawn_rotation, summarize_classic, track_classic, Classic, ClassicStartLevel,
+    },
+    clock::{tick_clock, GameClock},
+    combo::{combo_hud, combo_playfield, summarize_combo, track_combo, ComboTraining},
+    cpu::{play_cpu_moves, CpuPlayer, Difficulty},
+    dig::{add_dig_garbage, dig_hud, summarize_dig, track_dig, DigRace, DigSettings},
+    hold::{hold_piece, Hold},
+    invisible::{reveal_stack, summarize_invisible, HiddenStack, Invisible},
+    items::{activate_items, items_hud, roll_item, summarize_items, Item, Items},
+    marathon::{summarize_marathon, track_marathon, Marathon, MarathonSettings},
+    master::{
+        count_master_pieces, master_gravity, master_hud, summarize_master, track_master, Master,
+    },
+    mission::{mission_hud, summarize_mission, track_mission, Campaign},
+    mode::{mode_menu, GameMode},
+    net::{connection_window, net_error_window, NetError, NetPlugin},
+    perfect_clear::{
+        check_perfect_clear, pc_training_hud, summarize_pc_training, PcTraining, TRAINING_BAGS,
+    },
+    piece_order::{PieceOrder, Randomizer},
     piece_types::PieceType,
-    playfield::{Playfield, PlayfieldSize},
+    playfield::{ClearRule, Playfield, PlayfieldSize, TSpin},
+    puzzle::{
+        load_puzzles, puzzle_hud, summarize_puzzle, track_puzzle, PuzzleBoard, Puzzles,
+        SelectedPuzzle,
+    },
+    records::Records,
     render::RenderPlugin,
     rotation::Rotation,
+    sandbox::{run_sandbox, sandbox_hud, summarize_sandbox, Sandbox},
+    scoring::lock_points,
+    sprint::{sprint_hud, track_sprint, Sprint, SprintGoal},
+    survival::{
+        raise_survival_garbage, summarize_survival, survival_hud, Survival, SurvivalSettings,
+    },
+    ultra::{finish_ultra, ultra_hud, Ultra, ULTRA_TIME},
+    versus::{exchange_garbage, link_opponents, GarbageExchanged, PendingGarbage},
+    zen::{summarize_zen, zen_hud, Zen, ZenGravity, ZEN_CLEARED_ROWS},
 };
 
+pub use self::{spectator::SpectatorPlugin, tbp::TbpPlugin};
+
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(EntropyPlugin::<ChaCha8Rng>::default())
-            .insert_resource(PlayfieldSize([10, 24].into()))
-            .register_type::<Piece>()
+        app.add_plugins((GameLogicPlugin, RenderPlugin))
+            .add_systems(Update, mode_menu.run_if(in_state(GameState::Menu)))
+            .add_systems(Update, score_ui.run_if(in_state(GameState::InGame)))
             .add_systems(
-                OnEnter(GameState::SetupGame),
-                (setup_game, create_piece_order),
+                Update,
+                game_over_screen.run_if(in_state(GameState::GameOver)),
             )
             .add_systems(
                 Update,
-                (spawn_piece, move_piece).run_if(in_state(GameState::InGame)),
+                finished_screen.run_if(in_state(GameState::Finished)),
             )
-            .add_systems(OnExit(GameState::InGame), tear_down_game)
-            .add_systems(Update, score_ui.run_if(in_state(GameState::InGame)))
             .add_systems(
                 Update,
-                game_over_screen.run_if(in_state(GameState::GameOver)),
+                (
+                    sprint_hud,
+                    ultra_hud,
+                    zen_hud,
+                    dig_hud,
+                    survival_hud,
+                    master_hud,
+                    classic_hud,
+                    items_hud,
+                    puzzle_hud,
+                    mission_hud,
+                    pc_training_hud,
+                    combo_hud,
+                    sandbox_hud,
+                )
+                    .run_if(in_state(GameState::InGame)),
             )
-            .add_plugins(RenderPlugin);
+            .add_systems(
+                Update,
+                (
+                    connection_window.run_if(in_state(GameState::SetupGame)),
+                    net_error_window.run_if(resource_exists::<NetError>()),
+                ),
+            );
+    }
+}
+
+/// The rules of the game without any rendering or UI, so games can also run
+/// headless.
+pub struct GameLogicPlugin;
+
+impl Plugin for GameLogicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((EntropyPlugin::<ChaCha8Rng>::default(), NetPlugin))
+            .insert_resource(PlayfieldSize([10, 24].into()))
+            .register_type::<Piece>()
+            .register_type::<Board>()
+            .init_resource::<GameMode>()
+            .init_resource::<Difficulty>()
+            .init_resource::<SprintGoal>()
+            .init_resource::<MarathonSettings>()
+            .init_resource::<ZenGravity>()
+            .init_resource::<DigSettings>()
+            .init_resource::<SurvivalSettings>()
+            .init_resource::<ClassicStartLevel>()
+            .init_resource::<HiddenStack>()
+            .init_resource::<ClearRule>()
+            .init_resource::<MirrorMode>()
+            .init_resource::<SelectedPuzzle>()
+            .insert_resource(Records::load())
+            .add_event::<PieceLocked>()
+            .add_event::<GarbageExchanged>()
+            .add_systems(Startup, load_puzzles)
+            .add_systems(OnEnter(GameState::SetupGame), setup_game)
+            .add_systems(
+                Update,
+                (
+                    (
+                        (tick_clock, age_playfields),
+                        finish_ultra,
+                        exchange_garbage,
+                        add_dig_garbage,
+                        raise_survival_garbage,
+                        tick_spawn_delay,
+                        check_perfect_clear,
+                        run_sandbox,
+                        spawn_piece,
+                        count_master_pieces,
+                        (move_piece, classic_auto_shift, hold_piece).chain(),
+                        // pieces locked above are despawned before anything
+                        // else gets to lock them again
+                        apply_deferred,
+                        master_gravity,
+                        play_cpu_moves,
+                        (
+                            track_sprint,
+                            track_marathon,
+                            track_dig,
+                            track_master,
+                            track_classic,
+                            track_puzzle,
+                            track_mission,
+                            track_combo,
+                        ),
+                        reveal_stack,
+                        activate_items,
+                    )
+                        .chain(),
+                    check_game_over,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(
+                OnExit(GameState::InGame),
+                (
+                    (
+                        summarize_marathon,
+                        summarize_zen,
+                        summarize_dig,
+                        summarize_survival,
+                        summarize_master,
+                        summarize_classic,
+                        summarize_invisible,
+                        summarize_big,
+                        summarize_items,
+                        summarize_puzzle,
+                        summarize_mission,
+                        summarize_pc_training,
+                        summarize_combo,
+                        summarize_sandbox,
+                    ),
+                    tear_down_game,
+                )
+                    .chain(),
+            );
     }
 }
 
-fn setup_game(mut commands: Commands, playfield_size: Res<PlayfieldSize>) {
-    commands.insert_resource(StepTimer(Timer::from_seconds(1.0, TimerMode::Repeating)));
-    commands.insert_resource(Score { score: 0 });
-    commands.spawn((Name::new("Playfield"), Playfield::new(playfield_size.0)));
+fn setup_game(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    sprint_goal: Res<SprintGoal>,
+    marathon_settings: Res<MarathonSettings>,
+    zen_gravity: Res<ZenGravity>,
+    dig_settings: Res<DigSettings>,
+    survival_settings: Res<SurvivalSettings>,
+    classic_start_level: Res<ClassicStartLevel>,
+    hidden_stack: Res<HiddenStack>,
+    puzzles: Res<Puzzles>,
+    selected_puzzle: Res<SelectedPuzzle>,
+    playfield_size: Res<PlayfieldSize>,
+    clear_rule: Res<ClearRule>,
+    mirror_mode: Res<MirrorMode>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+) {
+    let PlayfieldSize(size) = *playfield_size;
+    // online boards are built by the net plugin and stay on naive gravity and
+    // unmirrored, the peer expects the same playfield
+    let new_board = |index, rng| {
+        BoardBundle::new(index, size, rng)
+            .with_clear_rule(*clear_rule)
+            .with_mirrored(mirror_mode.0)
+    };
+    commands.remove_resource::<MatchResult>();
+    commands.remove_resource::<GameSummary>();
+    commands.insert_resource(GameClock::default());
+    match *mode {
+        GameMode::Marathon => {
+            commands.spawn((
+                new_board(0, rng.fork_rng()).with_start_level(marathon_settings.start_level),
+                Controls::default(),
+                Marathon::new(*marathon_settings),
+            ));
+        }
+        GameMode::Sprint => {
+            commands.spawn((
+                new_board(0, rng.fork_rng()),
+                Controls::default(),
+                Sprint::new(*sprint_goal),
+            ));
+        }
+        GameMode::Ultra => {
+            commands.insert_resource(GameClock::with_limit(ULTRA_TIME));
+            commands.spawn((new_board(0, rng.fork_rng()), Controls::default(), Ultra));
+        }
+        GameMode::Zen => {
+            let mut board =
+                commands.spawn((new_board(0, rng.fork_rng()), Controls::default(), Zen));
+            match *zen_gravity {
+                ZenGravity::Fixed => board.insert(FixedGravity),
+                ZenGravity::Off => board.remove::<StepTimer>(),
+            };
+        }
+        GameMode::Dig => {
+            // the same seed gives the same garbage and pieces, so races can
+            // be compared
+            let seed = dig_settings.seed.unwrap_or_else(|| rng.next_u64());
+            commands.spawn((
+                new_board(0, EntropyComponent::seed_from_u64(seed)),
+                Controls::default(),
+                DigRace::new(*dig_settings, seed),
+            ));
+        }
+        GameMode::Survival => {
+            commands.spawn((
+                new_board(0, rng.fork_rng()),
+                Controls::default(),
+                Survival::new(*survival_settings, &mut *rng),
+            ));
+        }
+        GameMode::Master => {
+            commands
+                .spawn((
+                    new_board(0, rng.fork_rng()),
+                    Controls::default(),
+                    Master::default(),
+                ))
+                .remove::<StepTimer>();
+        }
+        GameMode::Classic => {
+            commands.spawn((
+                new_board(0, rng.fork_rng())
+                    .with_randomizer(Randomizer::Nes)
+                    .with_gravity(classic_gravity(classic_start_level.0)),
+                Controls::default(),
+                FixedGravity,
+                Classic::new(*classic_start_level),
+            ));
+        }
+        GameMode::Invisible => {
+            commands.spawn((
+                new_board(0, rng.fork_rng()),
+                Controls::default(),
+                Invisible::new(*hidden_stack),
+            ));
+        }
+        GameMode::Big => {
+            commands.spawn((new_board(0, rng.fork_rng()), Controls::default(), Big));
+        }
+        GameMode::Items => {
+            commands.spawn((
+                new_board(0, rng.fork_rng()),
+                Controls::default(),
+                Items::default(),
+            ));
+        }
+        GameMode::Puzzle => {
+            let puzzle = puzzles.puzzles[selected_puzzle.0].clone();
+            commands
+                .spawn((
+                    new_board(0, rng.fork_rng())
+                        .with_playfield(puzzle.playfield(size))
+                        .with_piece_order(puzzle.piece_order()),
+                    Controls::default(),
+                    Hold::new(puzzle.hold),
+                    PuzzleBoard::new(puzzle),
+                ))
+                .remove::<StepTimer>();
+        }
+        GameMode::Mission => {
+            commands.spawn((
+                new_board(0, rng.fork_rng()),
+                Controls::default(),
+                Campaign::default(),
+            ));
+        }
+        GameMode::PerfectClear => {
+            let mut board_rng = rng.fork_rng();
+            let piece_order = PieceOrder::known_bags(TRAINING_BAGS, &mut board_rng);
+            commands
+                .spawn((
+                    new_board(0, board_rng).with_piece_order(piece_order),
+                    Controls::default(),
+                    Hold::default(),
+                    PcTraining::default(),
+                ))
+                .remove::<StepTimer>();
+        }
+        GameMode::Combo => {
+            commands.spawn((
+                new_board(0, rng.fork_rng()).with_playfield(combo_playfield(size)),
+                Controls::default(),
+                Hold::default(),
+                ComboTraining::default(),
+            ));
+        }
+        GameMode::Sandbox => {
+            commands
+                .spawn((
+                    new_board(0, rng.fork_rng()),
+                    Controls::default(),
+                    Hold::default(),
+                    Sandbox::default(),
+                ))
+                .remove::<StepTimer>();
+        }
+        GameMode::Versus => {
+            // both players get the same pieces, garbage draws from a
+            // generator of its own so it can't change them
+            let pieces = rng.fork_rng();
+            let left = commands
+                .spawn((new_board(0, pieces.clone()), Controls::wasd()))
+                .id();
+            let right = commands
+                .spawn((new_board(1, pieces), Controls::arrows_and_numpad()))
+                .id();
+            link_opponents(&mut commands, left, right, rng.fork_inner());
+        }
+        GameMode::VersusCpu => {
+            // as above, and the computer misjudges with its own generator
+            let pieces = rng.fork_rng();
+            let player = commands
+                .spawn((new_board(0, pieces.clone()), Controls::default()))
+                .id();
+            let cpu = commands
+                .spawn((
+                    new_board(1, pieces).with_name(format!("CPU ({})", difficulty.label())),
+                    CpuPlayer::new(*difficulty, rng.fork_inner()),
+                ))
+                .id();
+            link_opponents(&mut commands, player, cpu, rng.fork_inner());
+        }
+        GameMode::Online => {
+            // the boards are spawned once the opponent is connected
+            return;
+        }
+    }
     commands.insert_resource(NextState(Some(GameState::InGame)));
 }
 
-fn tear_down_game(mut commands: Commands, playfield_query: Query<Entity, With<Playfield>>) {
-    commands.remove_resource::<StepTimer>();
-    commands.remove_resource::<Score>();
-    commands
-        .entity(playfield_query.single())
-        .despawn_recursive();
+fn tear_down_game(mut commands: Commands, board_query: Query<Entity, With<Board>>) {
+    for board in board_query.iter() {
+        commands.entity(board).despawn_recursive();
+    }
 }
 
-fn spawn_piece(
-    piece: Query<&Piece>,
+fn age_playfields(time: Res<Time>, mut playfield_query: Query<&mut Playfield>) {
+    for mut playfield in playfield_query.iter_mut() {
+        // only the cells matter for change detection
+        playfield.bypass_change_detection().tick(time.delta());
+    }
+}
+
+fn tick_spawn_delay(
     mut commands: Commands,
-    mut piece_order: ResMut<PieceOrder>,
-    rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
-    playfield_query: Query<&Playfield>,
+    time: Res<Time>,
+    mut board_query: Query<(Entity, &mut SpawnDelay)>,
 ) {
-    if let Err(QuerySingleError::NoEntities(_)) = piece.get_single() {
-        if piece_order.is_finished() {
-            *piece_order = PieceOrder::new(rng);
+    for (board, mut delay) in board_query.iter_mut() {
+        if delay.0.tick(time.delta()).finished() {
+            commands.entity(board).remove::<SpawnDelay>();
         }
-        let piece_type = piece_order.next_piece().expect("Should not be empty");
+    }
+}
 
-        let playfield = playfield_query.single();
+fn spawn_piece(
+    mut commands: Commands,
+    piece_query: Query<&Parent, With<Piece>>,
+    mut board_query: Query<
+        (
+            Entity,
+            &mut Playfield,
+            &mut PieceOrder,
+            &mut EntropyComponent<ChaCha8Rng>,
+            Has<Zen>,
+            Has<Classic>,
+            Has<Big>,
+            Has<Items>,
+        ),
+        (Without<ToppedOut>, Without<SpawnDelay>),
+    >,
+) {
+    for (board, mut playfield, mut piece_order, mut rng, zen, classic, big, items) in
+        board_query.iter_mut()
+    {
+        if piece_query.iter().any(|parent| parent.get() == board) {
+            continue;
+        }
+        piece_order.refill(&mut *rng);
+        let Some(piece_type) = piece_order.next_piece() else {
+            // only a fixed queue runs out
+            commands.entity(board).insert(ToppedOut);
+            continue;
+        };
 
-        let new_piece = Piece::new(piece_type);
+        let mut new_piece = if big {
+            Piece::big(piece_type)
+        } else {
+            Piece::new(piece_type)
+        }
+        .spawn(&playfield);
+        if classic {
+            new_piece.rotation = nintendo_spawn_rotation(piece_type);
+        }
+        if items {
+            new_piece.item = roll_item(&mut *rng);
+        }
+        if zen && !playfield.check_move(&new_piece) {
+            // zen boards never top out, the top of the stack makes room instead
+            playfield.clear_top_rows(ZEN_CLEARED_ROWS);
+        }
         if playfield.check_move(&new_piece) {
-            commands.spawn((Name::new("Current Piece"), new_piece));
+            commands.entity(board).with_children(|cb| {
+                cb.spawn((Name::new("Current Piece"), new_piece));
+            });
         } else {
-            commands.insert_resource(NextState(Some(GameState::GameOver)))
+            commands.entity(board).insert(ToppedOut);
         }
     }
 }
 
-#[derive(Resource)]
+/// The outcome of a game with more than one board.
+#[derive(Resource, Debug)]
+struct MatchResult {
+    winner: Option<String>,
+}
+
+/// A single board plays until it tops out, with several boards the last
+/// one standing wins.
+fn check_game_over(
+    mut commands: Commands,
+    board_query: Query<(&Name, Option<&ToppedOut>, Option<&Invisible>), With<Board>>,
+) {
+    let revealing = board_query.iter().any(|(_, topped_out, invisible)| {
+        topped_out.is_some() && invisible.is_some_and(Invisible::is_revealed)
+    });
+    if revealing {
+        // the hidden stack is shown for a moment before the game ends
+        return;
+    }
+
+    let boards = board_query.iter().len();
+    let standing: Vec<_> = board_query
+        .iter()
+        .filter(|(_, topped_out, _)| topped_out.is_none())
+        .map(|(name, _, _)| name)
+        .collect();
+
+    if boards == 0 || (!standing.is_empty() && (boards == 1 || standing.len() > 1)) {
+        return;
+    }
+
+    if boards > 1 {
+        commands.insert_resource(MatchResult {
+            winner: standing.first().map(|name| name.to_string()),
+        });
+    }
+    commands.insert_resource(NextState(Some(GameState::GameOver)))
+}
+
+/// What is shown when a game ends, either finished or over.
+#[derive(Resource, Debug)]
+struct GameSummary {
+    heading: String,
+    rows: Vec<(&'static str, String)>,
+}
+
+#[derive(Component)]
 struct StepTimer(Timer);
 
-#[derive(Reflect, Component, Debug)]
+#[derive(Reflect, Component, Debug, Clone, Copy)]
 pub struct Piece {
     position: IVec2,
     rotation: Rotation,
     piece_type: PieceType,
+    /// Whether the last successful move was a rotation, which is needed to
+    /// detect T-spins.
+    rotated_last: bool,
+    /// Width and height of every mino in cells.
+    scale: i32,
+    /// Item carried by the first mino.
+    item: Option<Item>,
+    /// Whether the piece is reflected left to right, as on a mirrored board.
+    mirrored: bool,
 }
 
 impl Piece {
@@ -102,6 +573,39 @@ impl Piece {
             piece_type,
             position: IVec2::new(5, 22),
             rotation: default(),
+            rotated_last: false,
+            scale: 1,
+            item: None,
+            mirrored: false,
+        }
+    }
+
+    /// The same piece reflected left to right on a playfield `width` cells
+    /// wide, so it covers the mirror image of the cells it covered.
+    fn mirrored(self, width: i32) -> Self {
+        Self {
+            position: IVec2::new(width - self.scale - self.position.x, self.position.y),
+            mirrored: !self.mirrored,
+            ..self
+        }
+    }
+
+    /// A new piece on a board that may be played mirrored.
+    fn spawn(self, playfield: &Playfield) -> Self {
+        if playfield.is_mirrored() {
+            self.mirrored(playfield.size().x as i32)
+        } else {
+            self
+        }
+    }
+
+    /// A piece of 2×2 minos. It spawns and moves on even cells only, so its
+    /// minos always line up with the ones of earlier big pieces.
+    fn big(piece_type: PieceType) -> Self {
+        Self {
+            position: IVec2::new(4, 20),
+            scale: 2,
+            ..Self::new(piece_type)
         }
     }
 }
@@ -109,59 +613,174 @@ impl Piece {
 fn move_piece(
     mut commands: Commands,
     time: Res<Time>,
-    mut timer: ResMut<StepTimer>,
-    mut query: Query<(Entity, &mut Piece)>,
-    mut playfield_query: Query<&mut Playfield>,
     keys: Res<Input<KeyCode>>,
-    mut score: ResMut<Score>,
+    mut locked_events: EventWriter<PieceLocked>,
+    mut piece_query: Query<(Entity, &mut Piece, &Parent)>,
+    mut board_query: Query<(
+        &mut Playfield,
+        Option<&mut StepTimer>,
+        Option<&mut LockDelay>,
+        &mut Score,
+        Option<&Controls>,
+        Has<FixedGravity>,
+        Has<Classic>,
+    )>,
 ) {
-    let Ok((entity, mut piece)) = query.get_single_mut() else {
-        return;
-    };
-    let mut playfield = playfield_query.single_mut();
-    if keys.just_pressed(KeyCode::Up) {
-        use Rotation::*;
-
-        let new_rotation = match piece.rotation {
-            R0 => R90,
-            R90 => R180,
-            R180 => R270,
-            R270 => R0,
+    for (entity, mut piece, parent) in piece_query.iter_mut() {
+        let board = parent.get();
+        let Ok((mut playfield, timer, lock_delay, mut score, controls, fixed_gravity, classic)) =
+            board_query.get_mut(board)
+        else {
+            continue;
         };
 
+        let before = (piece.position, piece.rotation);
+        let hard_dropped = controls
+            .is_some_and(|controls| handle_input(&keys, controls, &mut piece, &playfield, classic));
+
+        let delayed = lock_delay.is_some();
+        if let Some(mut lock_delay) = lock_delay {
+            let grounded = !playfield.check_move(&Piece {
+                position: piece.position - IVec2::Y * piece.scale,
+                ..*piece
+            });
+            if !grounded || (piece.position, piece.rotation) != before {
+                lock_delay.0.reset();
+            }
+            // a hard drop locks at once instead of waiting
+            if hard_dropped || (grounded && lock_delay.0.tick(time.delta()).finished()) {
+                lock_delay.0.reset();
+                lock_piece(
+                    &mut commands,
+                    &mut locked_events,
+                    board,
+                    entity,
+                    &piece,
+                    &mut playfield,
+                    &mut score,
+                );
+                continue;
+            }
+        }
+
+        let Some(mut timer) = timer else {
+            // without gravity a piece only locks when it is hard dropped
+            if hard_dropped {
+                lock_piece(
+                    &mut commands,
+                    &mut locked_events,
+                    board,
+                    entity,
+                    &piece,
+                    &mut playfield,
+                    &mut score,
+                );
+            }
+            continue;
+        };
+
+        if timer.0.tick(time.delta()).just_finished() {
+            let new_pos = piece.position - IVec2::Y * piece.scale;
+
+            let move_possible = playfield.check_move(&Piece {
+                position: new_pos,
+                ..*piece
+            });
+
+            if move_possible {
+                piece.position = new_pos;
+                piece.rotated_last = false;
+            } else if !delayed {
+                lock_piece(
+                    &mut commands,
+                    &mut locked_events,
+                    board,
+                    entity,
+                    &piece,
+                    &mut playfield,
+                    &mut score,
+                );
+            }
+
+            if !fixed_gravity {
+                timer.0.set_duration(Duration::from_secs_f32(score.speed()))
+            }
+        }
+    }
+}
+
+/// Moves the piece according to the pressed keys. Returns whether it was
+/// hard dropped.
+///
+/// Classic boards rotate the Nintendo way without wall kicks and have no
+/// hard drop.
+fn handle_input(
+    keys: &Input<KeyCode>,
+    controls: &Controls,
+    piece: &mut Piece,
+    playfield: &Playfield,
+    classic: bool,
+) -> bool {
+    let controls = &controls.for_piece(piece);
+    let new_rotation = if keys.just_pressed(controls.rotate_ccw) {
+        Some(piece.rotation.ccw())
+    } else if keys.just_pressed(controls.rotate_cw) {
+        Some(piece.rotation.cw())
+    } else {
+        None
+    };
+    let new_rotation = new_rotation.map(|rotation| {
+        if classic {
+            nintendo_rotation(piece.piece_type, rotation)
+        } else {
+            rotation
+        }
+    });
+
+    if let Some(new_rotation) = new_rotation {
         let check_result = playfield.check_rotation(&Piece {
             rotation: new_rotation,
             ..*piece
         });
 
-        if let CheckRotationResult::ValidWithOffset(offset) = check_result {
-            *piece = Piece {
-                rotation: new_rotation,
-                position: piece.position + offset,
-                ..*piece
+        match check_result {
+            // classic pieces never kick, a rotation that needs one is dropped
+            // but the rest of the input still counts
+            CheckRotationResult::ValidWithOffset(offset) if classic && offset != IVec2::ZERO => {}
+            CheckRotationResult::ValidWithOffset(offset) => {
+                *piece = Piece {
+                    rotation: new_rotation,
+                    position: piece.position + offset,
+                    rotated_last: true,
+                    ..*piece
+                }
             }
+            CheckRotationResult::Invalid => {}
         }
     }
 
-    if keys.just_pressed(KeyCode::Space) {
+    let hard_dropped = !classic && keys.just_pressed(controls.hard_drop);
+    if hard_dropped {
         let old_pos = piece.position;
         while playfield.check_move(&Piece {
-            position: piece.position + IVec2::NEG_Y,
+            position: piece.position + IVec2::NEG_Y * piece.scale,
             ..*piece
         }) {
-            piece.position += IVec2::NEG_Y;
+            piece.position += IVec2::NEG_Y * piece.scale;
         }
         let new_pos = piece.position;
 
-        if new_pos != old_pos {}
+        if new_pos != old_pos {
+            piece.rotated_last = false;
+        }
     }
 
     let direction = {
-        if keys.just_pressed(KeyCode::Right) {
+        if keys.just_pressed(controls.right) {
             Some(IVec2::X)
-        } else if keys.just_pressed(KeyCode::Left) {
+        } else if keys.just_pressed(controls.left) {
             Some(IVec2::NEG_X)
-        } else if keys.just_pressed(KeyCode::Down) {
+        } else if keys.just_pressed(controls.soft_drop) {
             Some(IVec2::NEG_Y)
         } else {
             None
@@ -169,7 +788,7 @@ fn move_piece(
     };
 
     if let Some(direction) = direction {
-        let new_pos = piece.position + direction;
+        let new_pos = piece.position + direction * piece.scale;
         let move_possible = playfield.check_move(&Piece {
             position: new_pos,
             ..*piece
@@ -177,57 +796,217 @@ fn move_piece(
 
         if move_possible {
             piece.position = new_pos;
+            piece.rotated_last = false;
         }
     }
 
-    if timer.0.tick(time.delta()).just_finished() {
-        let new_pos = piece.position - IVec2::Y;
-
-        let move_possible = playfield.check_move(&Piece {
-            position: new_pos,
-            ..*piece
-        });
+    hard_dropped
+}
 
-        if move_possible {
-            piece.position = new_pos;
-        } else {
-            commands.entity(entity).despawn_recursive();
-            playfield.set_cells(&piece);
-            let cleared_rows = playfield.clear_rows();
+/// Sent whenever a piece becomes part of the playfield of `board`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PieceLocked {
+    pub board: Entity,
+    /// The piece where it locked.
+    pub piece: Piece,
+    pub cleared_rows: usize,
+    /// Clear steps of a cascade, 1 for a plain clear and 0 without one.
+    pub chain: u32,
+    pub t_spin: TSpin,
+    /// Number of consecutive locks that cleared lines, including this one.
+    pub combo: u32,
+    /// Whether this clear continues a back-to-back chain of tetrises and
+    /// T-spins.
+    pub back_to_back: bool,
+    pub perfect_clear: bool,
+}
 
-            score.score += cleared_rows as u32;
-        }
+fn lock_piece(
+    commands: &mut Commands,
+    locked_events: &mut EventWriter<PieceLocked>,
+    board: Entity,
+    entity: Entity,
+    piece: &Piece,
+    playfield: &mut Playfield,
+    score: &mut Score,
+) {
+    commands.entity(entity).despawn_recursive();
+    place_piece(locked_events, board, piece, playfield, score);
+}
 
-        timer.0.set_duration(Duration::from_secs_f32(score.speed()))
+/// Adds the piece to the playfield and scores it, for pieces that have no
+/// entity of their own.
+fn place_piece(
+    locked_events: &mut EventWriter<PieceLocked>,
+    board: Entity,
+    piece: &Piece,
+    playfield: &mut Playfield,
+    score: &mut Score,
+) {
+    let t_spin = playfield.t_spin(piece);
+    playfield.set_cells(piece);
+    let garbage_rows = playfield.garbage_rows();
+    // a line of big minos is as tall as the piece scale
+    let chain: Vec<_> = playfield
+        .clear_chain()
+        .into_iter()
+        .map(|rows| rows / piece.scale as usize)
+        .collect();
+    let cleared_rows = chain.iter().sum::<usize>();
+    let perfect_clear = cleared_rows > 0 && playfield.is_empty();
+
+    let mut back_to_back = false;
+    if cleared_rows > 0 {
+        score.combo += 1;
+        let difficult = cleared_rows >= 4 || t_spin != TSpin::None;
+        back_to_back = difficult && score.back_to_back;
+        score.back_to_back = difficult;
+    } else {
+        score.combo = 0;
     }
+
+    score.score += lock_points(
+        &chain,
+        t_spin,
+        back_to_back,
+        score.combo,
+        perfect_clear,
+        score.level(),
+    );
+    score.lines += cleared_rows as u32;
+    score.garbage_lines += (garbage_rows - playfield.garbage_rows()) as u32;
+    score.pieces += 1;
+
+    locked_events.send(PieceLocked {
+        board,
+        piece: *piece,
+        cleared_rows,
+        chain: chain.len() as u32,
+        t_spin,
+        combo: score.combo,
+        back_to_back,
+        perfect_clear,
+    });
 }
 
-#[derive(Debug, Resource)]
+#[derive(Debug, Component, Clone)]
 struct Score {
     score: u32,
+    start_level: u32,
+    lines: u32,
+    /// Cleared lines that had garbage in them.
+    garbage_lines: u32,
+    pieces: u32,
+    /// Consecutive locks that cleared lines.
+    combo: u32,
+    /// Whether the last clear was a tetris or a T-spin.
+    back_to_back: bool,
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self {
+            score: 0,
+            start_level: 1,
+            lines: 0,
+            garbage_lines: 0,
+            pieces: 0,
+            combo: 0,
+            back_to_back: false,
+        }
+    }
 }
 
 impl Score {
+    /// The level rises every 10 cleared lines.
     fn level(&self) -> u32 {
-        self.score / 5
+        self.start_level + self.lines / 10
     }
 
+    /// Seconds per row of gravity, following the guideline curve.
     fn speed(&self) -> f32 {
-        1.0 * (0.75f32.powf(self.level() as f32))
+        let level = self.level() as f32 - 1.0;
+        (0.8 - level * 0.007).max(0.0).powf(level).max(0.001)
     }
 }
 
-fn score_ui(mut contexts: EguiContexts, score: Res<Score>) {
+fn score_ui(
+    mut contexts: EguiContexts,
+    board_query: Query<
+        (&Name, &Score, Option<&PendingGarbage>),
+        (With<Board>, Without<Master>, Without<Classic>),
+    >,
+) {
+    if board_query.is_empty() {
+        // the board keeps its own score, e.g. in master or classic
+        return;
+    }
+    let show_names = board_query.iter().len() > 1;
     egui::Window::new("Bevy Tetris").show(contexts.ctx_mut(), |ui| {
-        ui.label(format!("Current Score: {}", score.score));
-        ui.label(format!("Current Level: {}", score.level() + 1));
+        for (name, score, pending_garbage) in board_query.iter() {
+            if show_names {
+                ui.heading(name.as_str());
+            }
+            ui.label(format!("Current Score: {}", score.score));
+            ui.label(format!("Current Level: {}", score.level()));
+            ui.label(format!("Lines: {}", score.lines));
+            if let Some(pending_garbage) = pending_garbage {
+                ui.label(format!("Incoming Garbage: {}", pending_garbage.rows));
+            }
+        }
     });
 }
 
-fn game_over_screen(mut contexts: EguiContexts, mut commands: Commands) {
+fn game_over_screen(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    match_result: Option<Res<MatchResult>>,
+    summary: Option<Res<GameSummary>>,
+) {
     egui::Window::new("GAME OVER").show(contexts.ctx_mut(), |ui| {
+        if let Some(match_result) = match_result {
+            match &match_result.winner {
+                Some(winner) => ui.heading(format!("{winner} wins!")),
+                None => ui.heading("Draw!"),
+            };
+        }
+        if let Some(summary) = summary {
+            show_summary(ui, &summary);
+        }
+        if ui.button("Restart!").clicked() {
+            commands.insert_resource(NextState(Some(GameState::SetupGame)))
+        }
+        if ui.button("Main Menu").clicked() {
+            commands.insert_resource(NextState(Some(GameState::Menu)))
+        }
+    });
+}
+
+fn finished_screen(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    summary: Option<Res<GameSummary>>,
+) {
+    egui::Window::new("FINISHED").show(contexts.ctx_mut(), |ui| {
+        if let Some(summary) = summary {
+            show_summary(ui, &summary);
+        }
         if ui.button("Restart!").clicked() {
             commands.insert_resource(NextState(Some(GameState::SetupGame)))
         }
+        if ui.button("Main Menu").clicked() {
+            commands.insert_resource(NextState(Some(GameState::Menu)))
+        }
+    });
+}
+
+fn show_summary(ui: &mut egui::Ui, summary: &GameSummary) {
+    ui.heading(&summary.heading);
+    egui::Grid::new("summary").show(ui, |ui| {
+        for (label, value) in &summary.rows {
+            ui.label(*label);
+            ui.label(value);
+            ui.end_row();
+        }
     });
 }
@@ -0,0 +1,325 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::setup::GameState;
+
+use super::{
+    board::MirrorMode,
+    classic::{ClassicStartLevel, MAX_CLASSIC_START_LEVEL},
+    cpu::Difficulty,
+    dig::DigSettings,
+    invisible::HiddenStack,
+    marathon::{MarathonSettings, MARATHON_LINES, MAX_START_LEVEL},
+    net::{NetRole, NetSettings},
+    playfield::ClearRule,
+    puzzle::{Puzzles, SelectedPuzzle},
+    sprint::SprintGoal,
+    survival::{RiseCurve, SurvivalHoles, SurvivalSettings, RISE_INTERVAL_RANGE},
+    zen::ZenGravity,
+};
+
+/// The kind of game that is set up when entering [`GameState::SetupGame`].
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Marathon,
+    Sprint,
+    Ultra,
+    Zen,
+    Dig,
+    Survival,
+    Master,
+    Classic,
+    Invisible,
+    Big,
+    Items,
+    Puzzle,
+    Mission,
+    PerfectClear,
+    Combo,
+    Sandbox,
+    Versus,
+    VersusCpu,
+    Online,
+}
+
+impl GameMode {
+    const ALL: [GameMode; 19] = [
+        GameMode::Marathon,
+        GameMode::Sprint,
+        GameMode::Ultra,
+        GameMode::Zen,
+        GameMode::Dig,
+        GameMode::Survival,
+        GameMode::Master,
+        GameMode::Classic,
+        GameMode::Invisible,
+        GameMode::Big,
+        GameMode::Items,
+        GameMode::Puzzle,
+        GameMode::Mission,
+        GameMode::PerfectClear,
+        GameMode::Combo,
+        GameMode::Sandbox,
+        GameMode::Versus,
+        GameMode::VersusCpu,
+        GameMode::Online,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            GameMode::Marathon => "Marathon",
+            GameMode::Sprint => "Sprint",
+            GameMode::Ultra => "Ultra",
+            GameMode::Zen => "Zen",
+            GameMode::Dig => "Dig Race",
+            GameMode::Survival => "Survival",
+            GameMode::Master => "Master",
+            GameMode::Classic => "Classic",
+            GameMode::Invisible => "Invisible",
+            GameMode::Big => "Big",
+            GameMode::Items => "Items",
+            GameMode::Puzzle => "Puzzle",
+            GameMode::Mission => "Missions",
+            GameMode::PerfectClear => "PC training",
+            GameMode::Combo => "Combo training",
+            GameMode::Sandbox => "Sandbox",
+            GameMode::Versus => "Versus",
+            GameMode::VersusCpu => "Versus CPU",
+            GameMode::Online => "Online",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            GameMode::Marathon => "Clear 150 lines while gravity rises every 10 lines.",
+            GameMode::Sprint => "Clear the lines as fast as possible.",
+            GameMode::Ultra => "Score as much as possible in two minutes.",
+            GameMode::Zen => "Relaxed practice that never tops out.",
+            GameMode::Dig => "Clear all the garbage as fast as possible.",
+            GameMode::Survival => "Hold out as long as possible against rising garbage.",
+            GameMode::Master => "Arcade rules up to level 999 and 20G, graded by points and time.",
+            GameMode::Classic => {
+                "The rules of NES Tetris: no wall kicks, no hard drop, one next piece."
+            }
+            GameMode::Invisible => "Play on with a stack you cannot see.",
+            GameMode::Big => "Every mino is twice as wide and tall.",
+            GameMode::Items => "Some pieces carry an item that goes off when its line clears.",
+            GameMode::Puzzle => "Reach the goal of a prepared board with the pieces given.",
+            GameMode::Mission => "Complete one objective after another before the time runs out.",
+            GameMode::PerfectClear => {
+                "Practise perfect clears with known bags and a solver to check you."
+            }
+            GameMode::Combo => "Keep a combo going in a four wide well whose walls grow back.",
+            GameMode::Sandbox => "Study openers with undo, a queue of your own and any gravity.",
+            GameMode::Versus => {
+                "Two players on one keyboard. Left: WASD, Q/E to rotate. \
+                 Right: arrows, numpad 1/3 to rotate."
+            }
+            GameMode::VersusCpu => "Practise versus against the computer.",
+            GameMode::Online => "Versus against another game over the network.",
+        }
+    }
+}
+
+pub(super) fn mode_menu(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut difficulty: ResMut<Difficulty>,
+    mut sprint_goal: ResMut<SprintGoal>,
+    mut marathon_settings: ResMut<MarathonSettings>,
+    mut zen_gravity: ResMut<ZenGravity>,
+    mut dig_settings: ResMut<DigSettings>,
+    mut survival_settings: ResMut<SurvivalSettings>,
+    mut classic_start_level: ResMut<ClassicStartLevel>,
+    mut hidden_stack: ResMut<HiddenStack>,
+    mut clear_rule: ResMut<ClearRule>,
+    mut mirror_mode: ResMut<MirrorMode>,
+    puzzles: Res<Puzzles>,
+    mut selected_puzzle: ResMut<SelectedPuzzle>,
+    mut net_settings: ResMut<NetSettings>,
+) {
+    egui::Window::new("Bevy Tetris").show(contexts.ctx_mut(), |ui| {
+        for mode in GameMode::ALL {
+            ui.horizontal(|ui| {
+                // there is nothing to play without puzzles
+                let playable = mode != GameMode::Puzzle || !puzzles.puzzles.is_empty();
+                if ui
+                    .add_enabled(playable, egui::Button::new(mode.label()))
+                    .on_hover_text(mode.description())
+                    .clicked()
+                {
+                    commands.insert_resource(mode);
+                    commands.insert_resource(NextState(Some(GameState::SetupGame)));
+                }
+
+                if mode == GameMode::Marathon {
+                    ui.add(
+                        egui::DragValue::new(&mut marathon_settings.start_level)
+                            .clamp_range(1..=MAX_START_LEVEL)
+                            .prefix("Level "),
+                    );
+                    ui.checkbox(&mut marathon_settings.endless, "Endless")
+                        .on_hover_text(format!("Keep playing after {MARATHON_LINES} lines"));
+                }
+
+                if mode == GameMode::Sprint {
+                    egui::ComboBox::from_id_source("sprint_goal")
+                        .selected_text(format!("{} lines", sprint_goal.0))
+                        .show_ui(ui, |ui| {
+                            for option in SprintGoal::ALL {
+                                ui.selectable_value(
+                                    &mut *sprint_goal,
+                                    option,
+                                    format!("{} lines", option.0),
+                                );
+                            }
+                        });
+                }
+
+                if mode == GameMode::Zen {
+                    egui::ComboBox::from_id_source("zen_gravity")
+                        .selected_text(zen_gravity.label())
+                        .show_ui(ui, |ui| {
+                            for option in ZenGravity::ALL {
+                                ui.selectable_value(&mut *zen_gravity, option, option.label());
+                            }
+                        });
+                }
+
+                if mode == GameMode::Dig {
+                    egui::ComboBox::from_id_source("dig_rows")
+                        .selected_text(format!("{} rows", dig_settings.rows))
+                        .show_ui(ui, |ui| {
+                            for option in DigSettings::ROWS {
+                                ui.selectable_value(
+                                    &mut dig_settings.rows,
+                                    option,
+                                    format!("{option} rows"),
+                                );
+                            }
+                        });
+                    ui.checkbox(&mut dig_settings.rising, "Rising")
+                        .on_hover_text("New garbage rises while digging");
+                    let mut fixed_seed = dig_settings.seed.is_some();
+                    if ui
+                        .checkbox(&mut fixed_seed, "Seed")
+                        .on_hover_text("Race the same garbage and pieces again")
+                        .changed()
+                    {
+                        dig_settings.seed = fixed_seed.then_some(0);
+                    }
+                    if let Some(seed) = &mut dig_settings.seed {
+                        ui.add(egui::DragValue::new(seed));
+                    }
+                }
+
+                if mode == GameMode::Survival {
+                    let settings = &mut *survival_settings;
+                    ui.add(
+                        egui::DragValue::new(&mut settings.rise_interval)
+                            .clamp_range(RISE_INTERVAL_RANGE)
+                            .speed(0.1)
+                            .prefix("Every ")
+                            .suffix(" s"),
+                    )
+                    .on_hover_text("Time between two rising rows at the start");
+                    egui::ComboBox::from_id_source("survival_holes")
+                        .selected_text(settings.holes.label())
+                        .show_ui(ui, |ui| {
+                            for option in SurvivalHoles::ALL {
+                                ui.selectable_value(&mut settings.holes, option, option.label());
+                            }
+                        });
+                    egui::ComboBox::from_id_source("survival_curve")
+                        .selected_text(settings.curve.label())
+                        .show_ui(ui, |ui| {
+                            for option in RiseCurve::ALL {
+                                ui.selectable_value(&mut settings.curve, option, option.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("How fast the garbage speeds up");
+                }
+
+                if mode == GameMode::Classic {
+                    ui.add(
+                        egui::DragValue::new(&mut classic_start_level.0)
+                            .clamp_range(0..=MAX_CLASSIC_START_LEVEL)
+                            .prefix("Level "),
+                    );
+                }
+
+                if mode == GameMode::Invisible {
+                    egui::ComboBox::from_id_source("hidden_stack")
+                        .selected_text(hidden_stack.label())
+                        .show_ui(ui, |ui| {
+                            for option in HiddenStack::ALL {
+                                ui.selectable_value(&mut *hidden_stack, option, option.label());
+                            }
+                        });
+                }
+
+                if mode == GameMode::Puzzle {
+                    if let Some(selected) = puzzles.puzzles.get(selected_puzzle.0) {
+                        egui::ComboBox::from_id_source("puzzle")
+                            .selected_text(&selected.name)
+                            .show_ui(ui, |ui| {
+                                for (index, puzzle) in puzzles.puzzles.iter().enumerate() {
+                                    ui.selectable_value(
+                                        &mut *selected_puzzle,
+                                        SelectedPuzzle(index),
+                                        &puzzle.name,
+                                    );
+                                }
+                            });
+                    }
+                }
+
+                if mode == GameMode::VersusCpu {
+                    egui::ComboBox::from_id_source("cpu_difficulty")
+                        .selected_text(difficulty.label())
+                        .show_ui(ui, |ui| {
+                            for option in Difficulty::ALL {
+                                ui.selectable_value(&mut *difficulty, option, option.label());
+                            }
+                        });
+                }
+
+                if mode == GameMode::Online {
+                    let settings = &mut *net_settings;
+                    for role in [NetRole::Host, NetRole::Join] {
+                        if ui.radio(settings.role == role, role.label()).clicked() {
+                            settings.role = role;
+                            settings.address = role.default_address().to_string();
+                        }
+                    }
+                    ui.text_edit_singleline(&mut settings.address);
+                }
+            });
+        }
+
+        for error in &puzzles.errors {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        ui.separator();
+        egui::ComboBox::from_id_source("clear_rule")
+            .selected_text(clear_rule.label())
+            .show_ui(ui, |ui| {
+                for option in ClearRule::ALL {
+                    ui.selectable_value(&mut *clear_rule, option, option.label());
+                }
+            })
+            .response
+            .on_hover_text(
+                "What the blocks above a line clear do, online games always use naive gravity",
+            );
+        ui.checkbox(&mut mirror_mode.0, "Mirror boards")
+            .on_hover_text(
+                "Plays the boards mirrored left to right, J and L as well as S and Z trade \
+                 shapes. They are shown flipped back with left and right swapped, so a game \
+                 looks as usual as long as the rules are symmetric",
+            );
+    });
+}
@@ -0,0 +1,164 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        mpsc::{channel, Receiver, TryRecvError},
+        Mutex,
+    },
+    thread,
+};
+
+use bevy::prelude::*;
+
+use super::NetMessage;
+
+/// Whether this game waits for an opponent or connects to one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetRole {
+    Host,
+    Join,
+}
+
+impl NetRole {
+    pub fn label(self) -> &'static str {
+        match self {
+            NetRole::Host => "Host",
+            NetRole::Join => "Join",
+        }
+    }
+
+    pub fn default_address(self) -> &'static str {
+        match self {
+            NetRole::Host => "0.0.0.0:7878",
+            NetRole::Join => "127.0.0.1:7878",
+        }
+    }
+}
+
+/// How an online game is set up. The host listens on `address`, the other
+/// side connects to it.
+#[derive(Resource, Debug, Clone)]
+pub struct NetSettings {
+    pub role: NetRole,
+    pub address: String,
+}
+
+impl Default for NetSettings {
+    fn default() -> Self {
+        Self {
+            role: NetRole::Host,
+            address: NetRole::Host.default_address().to_string(),
+        }
+    }
+}
+
+/// A connection that is being established. The host polls its listener,
+/// joining connects on a background thread, so the game keeps running in
+/// both cases.
+#[derive(Resource)]
+pub enum PendingConnection {
+    Listening(TcpListener),
+    Connecting(Mutex<Receiver<io::Result<TcpStream>>>),
+}
+
+impl PendingConnection {
+    pub fn open(settings: &NetSettings) -> io::Result<Self> {
+        match settings.role {
+            NetRole::Host => {
+                let listener = TcpListener::bind(&settings.address)?;
+                listener.set_nonblocking(true)?;
+                Ok(Self::Listening(listener))
+            }
+            NetRole::Join => {
+                let (sender, receiver) = channel();
+                let address = settings.address.clone();
+                thread::spawn(move || {
+                    let _ = sender.send(TcpStream::connect(address));
+                });
+                Ok(Self::Connecting(Mutex::new(receiver)))
+            }
+        }
+    }
+
+    /// The address the host listens on.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Self::Listening(listener) => listener.local_addr().ok(),
+            Self::Connecting(_) => None,
+        }
+    }
+
+    pub fn poll(&self) -> Option<io::Result<TcpStream>> {
+        match self {
+            Self::Listening(listener) => match listener.accept() {
+                Ok((stream, _)) => Some(stream.set_nonblocking(false).map(|_| stream)),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => None,
+                Err(e) => Some(Err(e)),
+            },
+            Self::Connecting(receiver) => receiver.lock().unwrap().try_recv().ok(),
+        }
+    }
+}
+
+pub enum NetEvent {
+    Message(NetMessage),
+    Disconnected,
+}
+
+/// An established connection. Messages are JSON lines, a background thread
+/// reads them from the socket.
+pub struct Connection {
+    stream: TcpStream,
+    messages: Mutex<Receiver<NetMessage>>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                match serde_json::from_str::<NetMessage>(&line) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => warn!("Ignoring network message {line:?}: {e}"),
+                }
+            }
+        });
+
+        Ok(Self {
+            stream,
+            messages: Mutex::new(receiver),
+        })
+    }
+
+    pub fn send(&mut self, message: &NetMessage) {
+        let mut line = serde_json::to_string(message).expect("messages serialize");
+        line.push('\n');
+        if let Err(e) = self.stream.write_all(line.as_bytes()) {
+            warn!("Could not send {message:?}: {e}");
+        }
+    }
+
+    pub fn poll(&self) -> Option<NetEvent> {
+        match self.messages.lock().unwrap().try_recv() {
+            Ok(message) => Some(NetEvent::Message(message)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(NetEvent::Disconnected),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
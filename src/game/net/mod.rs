@@ -0,0 +1,455 @@
+//! Versus against another game over TCP.
+//!
+//! Both games simulate their own board and send every lock to the other side,
+//! which replays it on a mirror of the opponent's board. A lock carries the
+//! garbage that was inserted with it, the garbage it sends on and a hash of
+//! the board afterwards, so both sides notice when their mirrors drift apart.
+
+mod connection;
+#[cfg(test)]
+mod tests;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::*;
+use rand_core::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::setup::GameState;
+
+use self::connection::{Connection, NetEvent, PendingConnection};
+
+use super::{
+    board::{BoardBundle, Controls, ToppedOut},
+    cpu::play_cpu_moves,
+    mode::GameMode,
+    move_piece,
+    piece_order::PieceOrder,
+    piece_types::PieceType,
+    place_piece,
+    playfield::{GarbagePattern, Playfield, PlayfieldSize},
+    rotation::Rotation,
+    versus::{exchange_garbage, GarbageExchanged, PendingGarbage},
+    Piece, PieceLocked, Score, StepTimer,
+};
+
+pub use self::connection::{NetRole, NetSettings};
+
+/// Bumped whenever messages or game rules change in a way that older games
+/// can not follow.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// The rules both games have to agree on before a match starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Ruleset {
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum NetMessage {
+    /// Sent by both sides right after connecting.
+    Hello {
+        version: u32,
+        ruleset: Ruleset,
+    },
+    /// Sent by the host once the rules match. Both boards draw their pieces
+    /// from the same seed.
+    Start {
+        seed: u64,
+    },
+    Lock(Lock),
+    ToppedOut,
+}
+
+/// A piece that locked on the board of the sender.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Lock {
+    piece_type: PieceType,
+    rotation: Rotation,
+    x: i32,
+    y: i32,
+    rotated_last: bool,
+    /// Rows and hole column of garbage inserted after the lock.
+    garbage: Option<(usize, usize)>,
+    /// Garbage rows sent to the receiver.
+    attack: u32,
+    /// [`Playfield::state_hash`] of the board after the lock.
+    hash: u64,
+}
+
+impl Lock {
+    fn piece(&self) -> Piece {
+        Piece {
+            position: IVec2::new(self.x, self.y),
+            rotation: self.rotation,
+            piece_type: self.piece_type,
+            rotated_last: self.rotated_last,
+            scale: 1,
+            item: None,
+            mirrored: false,
+        }
+    }
+}
+
+/// The board played in this game.
+#[derive(Component, Debug)]
+struct LocalBoard;
+
+/// The mirror of the opponent's board, which only changes by received locks.
+#[derive(Component, Debug)]
+struct RemoteBoard;
+
+/// Shown to the player when the connection failed or the games went out of
+/// sync.
+#[derive(Resource, Debug)]
+pub struct NetError(pub String);
+
+#[derive(Resource)]
+pub(super) struct NetSession {
+    connection: Connection,
+    role: NetRole,
+    /// Whether the opponent sent matching rules.
+    rules_agreed: bool,
+    locks_received: u32,
+    top_out_reported: bool,
+}
+
+pub(super) struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetSettings>()
+            .add_systems(
+                OnEnter(GameState::SetupGame),
+                open_connection.run_if(resource_equals(GameMode::Online)),
+            )
+            .add_systems(
+                Update,
+                (await_connection, handshake)
+                    .chain()
+                    .run_if(in_state(GameState::SetupGame)),
+            )
+            .add_systems(OnExit(GameState::SetupGame), cancel_connection)
+            .add_systems(
+                Update,
+                (
+                    receive_locks.before(exchange_garbage),
+                    (send_locks, report_top_out)
+                        .chain()
+                        .after(exchange_garbage)
+                        .after(move_piece)
+                        .after(play_cpu_moves),
+                )
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(resource_exists::<NetSession>()),
+            )
+            .add_systems(
+                OnExit(GameState::InGame),
+                (report_top_out, close_connection)
+                    .chain()
+                    .run_if(resource_exists::<NetSession>()),
+            );
+    }
+}
+
+fn ruleset(playfield_size: &PlayfieldSize) -> Ruleset {
+    Ruleset {
+        width: playfield_size.0.x,
+        height: playfield_size.0.y,
+    }
+}
+
+fn fail(commands: &mut Commands, message: String) {
+    error!("{message}");
+    commands.insert_resource(NetError(message));
+    commands.insert_resource(NextState(Some(GameState::Menu)));
+}
+
+fn open_connection(mut commands: Commands, settings: Res<NetSettings>) {
+    commands.remove_resource::<NetError>();
+    match PendingConnection::open(&settings) {
+        Ok(pending) => commands.insert_resource(pending),
+        Err(e) => fail(
+            &mut commands,
+            format!("Could not open {}: {e}", settings.address),
+        ),
+    }
+}
+
+fn await_connection(
+    mut commands: Commands,
+    pending: Option<Res<PendingConnection>>,
+    settings: Res<NetSettings>,
+    playfield_size: Res<PlayfieldSize>,
+) {
+    let Some(stream) = pending.and_then(|pending| pending.poll()) else {
+        return;
+    };
+    commands.remove_resource::<PendingConnection>();
+
+    match stream.and_then(Connection::new) {
+        Ok(mut connection) => {
+            connection.send(&NetMessage::Hello {
+                version: PROTOCOL_VERSION,
+                ruleset: ruleset(&playfield_size),
+            });
+            commands.insert_resource(NetSession {
+                connection,
+                role: settings.role,
+                rules_agreed: false,
+                locks_received: 0,
+                top_out_reported: false,
+            });
+        }
+        Err(e) => fail(
+            &mut commands,
+            format!("Could not connect to {}: {e}", settings.address),
+        ),
+    }
+}
+
+/// Checks the rules of the opponent and starts the match. The host picks the
+/// seed.
+fn handshake(
+    mut commands: Commands,
+    session: Option<ResMut<NetSession>>,
+    playfield_size: Res<PlayfieldSize>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+
+    while let Some(event) = session.connection.poll() {
+        let seed = match event {
+            NetEvent::Message(NetMessage::Hello {
+                version,
+                ruleset: rules,
+            }) => {
+                if version != PROTOCOL_VERSION || rules != ruleset(&playfield_size) {
+                    commands.remove_resource::<NetSession>();
+                    fail(
+                        &mut commands,
+                        "The opponent plays with different rules".to_string(),
+                    );
+                    return;
+                }
+                session.rules_agreed = true;
+                if session.role != NetRole::Host {
+                    continue;
+                }
+                let seed = rng.next_u64();
+                session.connection.send(&NetMessage::Start { seed });
+                seed
+            }
+            NetEvent::Message(NetMessage::Start { seed }) if session.rules_agreed => seed,
+            NetEvent::Message(message) => {
+                warn!("Unexpected message during setup: {message:?}");
+                continue;
+            }
+            NetEvent::Disconnected => {
+                commands.remove_resource::<NetSession>();
+                fail(&mut commands, "The opponent left".to_string());
+                return;
+            }
+        };
+
+        start_match(&mut commands, session.role, playfield_size.0, seed);
+        return;
+    }
+}
+
+fn start_match(commands: &mut Commands, role: NetRole, size: UVec2, seed: u64) {
+    let (local_name, remote_name) = match role {
+        NetRole::Host => ("Host", "Guest"),
+        NetRole::Join => ("Guest", "Host"),
+    };
+    let rng = EntropyComponent::<ChaCha8Rng>::seed_from_u64(seed);
+
+    commands.spawn((
+        BoardBundle::new(0, size, rng.clone()).with_name(local_name.to_string()),
+        Controls::default(),
+        // the peer learns the holes from the locks, they needn't match
+        PendingGarbage::new(ChaCha8Rng::seed_from_u64(seed.wrapping_add(1))),
+        LocalBoard,
+    ));
+    // the opponent's pieces arrive over the network, so the mirror neither
+    // spawns pieces nor has gravity
+    commands
+        .spawn((
+            BoardBundle::new(1, size, rng).with_name(remote_name.to_string()),
+            RemoteBoard,
+        ))
+        .remove::<(PieceOrder, StepTimer)>();
+
+    commands.insert_resource(NextState(Some(GameState::InGame)));
+}
+
+fn cancel_connection(mut commands: Commands) {
+    commands.remove_resource::<PendingConnection>();
+}
+
+fn send_locks(
+    mut session: ResMut<NetSession>,
+    mut exchanged_events: EventReader<GarbageExchanged>,
+    local_query: Query<(Entity, &Playfield), With<LocalBoard>>,
+) {
+    let Ok((local, playfield)) = local_query.get_single() else {
+        return;
+    };
+
+    for exchanged in exchanged_events
+        .read()
+        .filter(|exchanged| exchanged.locked.board == local)
+    {
+        let piece = exchanged.locked.piece;
+        session.connection.send(&NetMessage::Lock(Lock {
+            piece_type: piece.piece_type,
+            rotation: piece.rotation,
+            x: piece.position.x,
+            y: piece.position.y,
+            rotated_last: piece.rotated_last,
+            garbage: exchanged.inserted,
+            attack: exchanged.sent,
+            hash: playfield.state_hash(),
+        }));
+    }
+}
+
+/// Replays the locks of the opponent on its mirror and queues the garbage it
+/// sends.
+fn receive_locks(
+    mut commands: Commands,
+    mut session: ResMut<NetSession>,
+    mut locked_events: EventWriter<PieceLocked>,
+    mut local_query: Query<&mut PendingGarbage, With<LocalBoard>>,
+    mut remote_query: Query<
+        (
+            Entity,
+            &mut Playfield,
+            &mut Score,
+            &mut EntropyComponent<ChaCha8Rng>,
+            Option<&ToppedOut>,
+        ),
+        With<RemoteBoard>,
+    >,
+) {
+    let Ok((remote, mut playfield, mut score, mut rng, topped_out)) = remote_query.get_single_mut()
+    else {
+        return;
+    };
+
+    while let Some(event) = session.connection.poll() {
+        match event {
+            NetEvent::Message(NetMessage::Lock(lock)) => {
+                session.locks_received += 1;
+                // a lock that can't happen on the mirror is as out of sync as
+                // a wrong hash, and is not replayed
+                let piece = lock.piece();
+                let possible = playfield.check_move(&piece)
+                    && lock
+                        .garbage
+                        .is_none_or(|(rows, _)| rows <= playfield.size().y as usize);
+                if possible {
+                    place_piece(
+                        &mut locked_events,
+                        remote,
+                        &piece,
+                        &mut playfield,
+                        &mut score,
+                    );
+                    if let Some((rows, hole_column)) = lock.garbage {
+                        let pattern = GarbagePattern::Clean { hole_column };
+                        if playfield.add_garbage(rows, pattern, &mut *rng) {
+                            commands.entity(remote).insert(ToppedOut);
+                        }
+                    }
+                }
+
+                if !possible || playfield.state_hash() != lock.hash {
+                    let message = format!(
+                        "Out of sync with the opponent after its lock {}",
+                        session.locks_received
+                    );
+                    error!("{message}");
+                    commands.insert_resource(NetError(message));
+                    commands.insert_resource(NextState(Some(GameState::GameOver)));
+                    return;
+                }
+
+                if let Ok(mut pending) = local_query.get_single_mut() {
+                    pending.rows += lock.attack;
+                }
+            }
+            NetEvent::Message(NetMessage::ToppedOut) => {
+                // the match is over, the connection closing next is expected
+                commands.entity(remote).insert(ToppedOut);
+                return;
+            }
+            NetEvent::Message(message) => warn!("Unexpected message in game: {message:?}"),
+            NetEvent::Disconnected => {
+                // leaving counts as losing
+                if topped_out.is_none() {
+                    commands.entity(remote).insert(ToppedOut);
+                    commands.insert_resource(NetError("The opponent left".to_string()));
+                }
+                return;
+            }
+        }
+    }
+}
+
+fn report_top_out(
+    mut session: ResMut<NetSession>,
+    local_query: Query<(), (With<LocalBoard>, With<ToppedOut>)>,
+) {
+    if !session.top_out_reported && !local_query.is_empty() {
+        session.connection.send(&NetMessage::ToppedOut);
+        session.top_out_reported = true;
+    }
+}
+
+fn close_connection(mut commands: Commands) {
+    commands.remove_resource::<NetSession>();
+}
+
+pub(super) fn connection_window(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    settings: Res<NetSettings>,
+    pending: Option<Res<PendingConnection>>,
+    session: Option<Res<NetSession>>,
+) {
+    let status = match (pending.as_deref(), session) {
+        (Some(pending), _) => match pending.local_addr() {
+            Some(address) => format!("Waiting for an opponent on {address}"),
+            None => format!("Connecting to {}", settings.address),
+        },
+        (None, Some(_)) => "Agreeing on the rules".to_string(),
+        (None, None) => return,
+    };
+
+    egui::Window::new("Online").show(contexts.ctx_mut(), |ui| {
+        ui.label(status);
+        if ui.button("Cancel").clicked() {
+            commands.remove_resource::<NetSession>();
+            commands.insert_resource(NextState(Some(GameState::Menu)));
+        }
+    });
+}
+
+pub(super) fn net_error_window(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    error: Res<NetError>,
+) {
+    egui::Window::new("Network Error").show(contexts.ctx_mut(), |ui| {
+        ui.label(&error.0);
+        if ui.button("OK").clicked() {
+            commands.remove_resource::<NetError>();
+        }
+    });
+}
@@ -0,0 +1,208 @@
+//! Two headless games playing each other over localhost.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{ecs::event::ManualEventReader, prelude::*, time::TimeUpdateStrategy};
+use bevy_prng::ChaCha8Rng;
+use rand_core::SeedableRng;
+
+use crate::{
+    game::{
+        board::Controls,
+        cpu::{CpuPlayer, Difficulty},
+        mode::GameMode,
+        piece_types::PieceType,
+        playfield::{Cell, Playfield},
+        rotation::Rotation,
+        versus::GarbageExchanged,
+        GameLogicPlugin, MatchResult, StepTimer,
+    },
+    setup::GameState,
+};
+
+use super::{
+    connection::PendingConnection, LocalBoard, Lock, NetError, NetMessage, NetRole, NetSession,
+    NetSettings, RemoteBoard,
+};
+
+fn headless_game(role: NetRole, address: String) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, GameLogicPlugin))
+        .add_state::<GameState>()
+        .init_resource::<Input<KeyCode>>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            50,
+        )))
+        .insert_resource(GameMode::Online)
+        .insert_resource(NetSettings { role, address })
+        .insert_resource(NextState(Some(GameState::SetupGame)));
+    app
+}
+
+fn state(app: &App) -> GameState {
+    *app.world.resource::<State<GameState>>().get()
+}
+
+/// Updates both games until `done` holds. Messages need a moment to travel,
+/// so every round also sleeps a little.
+fn run_until(host: &mut App, guest: &mut App, mut done: impl FnMut(&mut App, &mut App) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while !done(host, guest) {
+        assert!(Instant::now() < deadline, "games did not finish in time");
+        host.update();
+        guest.update();
+        thread::sleep(Duration::from_micros(200));
+    }
+}
+
+/// Connects a host and a guest and lets the computer play both local boards.
+fn start_match(guest_difficulty: Difficulty) -> (App, App) {
+    let mut host = headless_game(NetRole::Host, "127.0.0.1:0".to_string());
+    host.update();
+    let address = host
+        .world
+        .resource::<PendingConnection>()
+        .local_addr()
+        .expect("host listens");
+    let mut guest = headless_game(NetRole::Join, address.to_string());
+
+    run_until(&mut host, &mut guest, |host, guest| {
+        state(host) == GameState::InGame && state(guest) == GameState::InGame
+    });
+
+    for (app, difficulty) in [
+        (&mut host, Difficulty::Hard),
+        (&mut guest, guest_difficulty),
+    ] {
+        let local = app
+            .world
+            .query_filtered::<Entity, With<LocalBoard>>()
+            .single(&app.world);
+        app.world
+            .entity_mut(local)
+            .remove::<Controls>()
+            .insert(CpuPlayer::new(difficulty, ChaCha8Rng::seed_from_u64(7)));
+    }
+
+    (host, guest)
+}
+
+#[test]
+fn match_stays_in_sync() {
+    let (mut host, mut guest) = start_match(Difficulty::Easy);
+
+    let mut garbage_readers = [ManualEventReader::<GarbageExchanged>::default(), default()];
+    let mut garbage_rows = [0; 2];
+    let mut locks_received = [0; 2];
+
+    run_until(&mut host, &mut guest, |host, guest| {
+        for (i, app) in [&*host, &*guest].into_iter().enumerate() {
+            let events = app.world.resource::<Events<GarbageExchanged>>();
+            garbage_rows[i] += garbage_readers[i]
+                .read(events)
+                .filter_map(|exchanged| exchanged.inserted)
+                .map(|(rows, _)| rows)
+                .sum::<usize>();
+            if let Some(session) = app.world.get_resource::<NetSession>() {
+                locks_received[i] = session.locks_received;
+            }
+        }
+        state(host) == GameState::GameOver && state(guest) == GameState::GameOver
+    });
+
+    for app in [&host, &guest] {
+        assert!(
+            app.world.get_resource::<NetError>().is_none(),
+            "{:?}",
+            app.world.get_resource::<NetError>()
+        );
+    }
+    assert!(locks_received.iter().all(|&locks| locks > 0));
+    assert!(
+        garbage_rows.iter().sum::<usize>() > 0,
+        "no garbage was sent"
+    );
+
+    let winner = |app: &App| app.world.resource::<MatchResult>().winner.clone();
+    assert_eq!(winner(&host), winner(&guest));
+}
+
+#[test]
+fn diverging_boards_are_detected() {
+    let (mut host, mut guest) = start_match(Difficulty::Hard);
+
+    // the guest's mirror of the host board gets a block the host never placed
+    let mut playfield = guest
+        .world
+        .query_filtered::<&mut Playfield, With<RemoteBoard>>()
+        .single_mut(&mut guest.world);
+    *playfield.get_mut(IVec2::new(0, 20)).unwrap() = Cell::Garbage;
+
+    run_until(&mut host, &mut guest, |_, guest| {
+        state(guest) == GameState::GameOver
+    });
+
+    let error = guest.world.resource::<NetError>();
+    assert!(error.0.starts_with("Out of sync"), "{error:?}");
+}
+
+/// Locks that could never happen on the mirror are desyncs, not replayed.
+#[test]
+fn impossible_locks_are_detected() {
+    let piece_in_the_floor = Lock {
+        piece_type: PieceType::T,
+        rotation: Rotation::R0,
+        x: 4,
+        y: -1,
+        rotated_last: false,
+        garbage: None,
+        attack: 0,
+        hash: 0,
+    };
+    let endless_garbage = Lock {
+        y: 12,
+        garbage: Some((1_000_000, 0)),
+        ..piece_in_the_floor
+    };
+
+    for lock in [piece_in_the_floor, endless_garbage] {
+        let (mut host, mut guest) = start_match(Difficulty::Easy);
+        // the host board stands still, so only the bad lock can arrive
+        let local = host
+            .world
+            .query_filtered::<Entity, With<LocalBoard>>()
+            .single(&host.world);
+        host.world
+            .entity_mut(local)
+            .remove::<(CpuPlayer, StepTimer)>();
+        let mut rounds = 0;
+        run_until(&mut host, &mut guest, |_, _| {
+            rounds += 1;
+            rounds > 20
+        });
+        let remote_hash = |guest: &mut App| {
+            guest
+                .world
+                .query_filtered::<&Playfield, With<RemoteBoard>>()
+                .single(&guest.world)
+                .state_hash()
+        };
+        let before = remote_hash(&mut guest);
+
+        host.world
+            .resource_mut::<NetSession>()
+            .connection
+            .send(&NetMessage::Lock(lock));
+        // the boards are still there in the frame the error is noticed
+        run_until(&mut host, &mut guest, |_, guest| {
+            guest.world.contains_resource::<NetError>()
+        });
+
+        let error = guest.world.resource::<NetError>();
+        assert!(error.0.starts_with("Out of sync"), "{error:?}");
+        assert_eq!(remote_hash(&mut guest), before, "the lock was replayed");
+    }
+}
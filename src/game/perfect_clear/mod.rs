@@ -0,0 +1,162 @@
+//! Practice for perfect clears, starting with the opener. The pieces are
+//! dealt in seven-bags that are all known up front, and after every lock a
+//! solver checks in the background whether a perfect clear can still be
+//! reached.
+
+mod solver;
+#[cfg(test)]
+mod tests;
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::setup::GameState;
+
+use self::solver::{solve, Solution};
+
+use super::{
+    clock::{format_time, GameClock},
+    hold::Hold,
+    piece_order::PieceOrder,
+    piece_types::piece_letter,
+    playfield::Playfield,
+    GameSummary, Piece, PieceLocked, Score,
+};
+
+/// Bags dealt in a training game, far more than anyone gets through.
+pub const TRAINING_BAGS: usize = 100;
+
+/// A board practising perfect clears.
+#[derive(Component, Debug)]
+pub struct PcTraining {
+    /// One way to a perfect clear, if there is still any.
+    solution: Option<Solution>,
+    show_solution: bool,
+    perfect_clears: u32,
+    /// Whether the solver has to look at the board again.
+    outdated: bool,
+    /// The search that is still running for the current board.
+    search: Option<Task<Option<Solution>>>,
+}
+
+impl Default for PcTraining {
+    fn default() -> Self {
+        Self {
+            solution: None,
+            show_solution: false,
+            perfect_clears: 0,
+            outdated: true,
+            search: None,
+        }
+    }
+}
+
+/// Starts the solver after every lock and hold, before the next piece is
+/// dealt, so the queue holds all pieces that are still to come. A search that
+/// is still running for an older board is dropped, which cancels it.
+pub(super) fn check_perfect_clear(
+    mut locked_events: EventReader<PieceLocked>,
+    piece_query: Query<(&Piece, &Parent)>,
+    mut training_query: Query<(Entity, &mut PcTraining, &Playfield, &PieceOrder, Ref<Hold>)>,
+) {
+    for locked in locked_events.read() {
+        let Ok((_, mut training, ..)) = training_query.get_mut(locked.board) else {
+            continue;
+        };
+        training.perfect_clears += u32::from(locked.perfect_clear);
+        training.outdated = true;
+    }
+
+    for (board, mut training, playfield, piece_order, hold) in training_query.iter_mut() {
+        if training.outdated || hold.is_changed() {
+            training.outdated = false;
+            training.solution = None;
+            let playfield = playfield.clone();
+            // a piece taken out of the hold is falling already and comes first
+            let falling = piece_query
+                .iter()
+                .find(|(_, parent)| parent.get() == board)
+                .map(|(piece, _)| piece.piece_type);
+            let queue: Vec<_> = falling
+                .into_iter()
+                .chain(piece_order.upcoming().iter().copied())
+                .collect();
+            let hold = hold.piece;
+            training.search = Some(
+                AsyncComputeTaskPool::get().spawn(async move { solve(&playfield, &queue, hold) }),
+            );
+        }
+
+        if training.search.as_ref().is_some_and(Task::is_finished) {
+            if let Some(search) = training.search.take() {
+                training.solution = block_on(search);
+            }
+        }
+    }
+}
+
+pub(super) fn pc_training_hud(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut training_query: Query<&mut PcTraining>,
+) {
+    for mut training in training_query.iter_mut() {
+        egui::Window::new("Perfect clear").show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("Perfect clears: {}", training.perfect_clears));
+            if ui.button("New bags").clicked() {
+                commands.insert_resource(NextState(Some(GameState::SetupGame)));
+            }
+            if training.search.is_some() {
+                ui.label("Searching...");
+                return;
+            }
+            let Some(solution) = &training.solution else {
+                ui.colored_label(egui::Color32::RED, "No perfect clear left");
+                return;
+            };
+            ui.colored_label(egui::Color32::GREEN, "Perfect clear possible");
+            let grid = solution_grid(solution);
+            ui.checkbox(&mut training.show_solution, "Show a solution");
+            if training.show_solution {
+                ui.monospace(grid);
+            }
+        });
+    }
+}
+
+/// Draws the window with the letter of the piece that fills each cell, the
+/// cells that are filled already show as `#`.
+fn solution_grid(solution: &Solution) -> String {
+    let mut grid = vec![['#'; 10]; solution.rows];
+    for placement in &solution.placements {
+        for cell in placement.cells {
+            grid[cell.y as usize][cell.x as usize] = piece_letter(placement.piece_type);
+        }
+    }
+    grid.iter()
+        .rev()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Sums up the game before the board is torn down.
+pub(super) fn summarize_pc_training(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    training_query: Query<(&PcTraining, &Score)>,
+) {
+    for (training, score) in training_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: format!("{} perfect clears", training.perfect_clears),
+            rows: vec![
+                ("Pieces", score.pieces.to_string()),
+                ("Lines", score.lines.to_string()),
+                ("Time", format_time(clock.elapsed)),
+            ],
+        });
+    }
+}
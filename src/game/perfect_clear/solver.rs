@@ -0,0 +1,322 @@
+//! Searches for perfect clears in the bottom rows of a playfield. The rows
+//! are packed into the bits of a `u64`, ten bits per row with the bottom row
+//! first, which keeps every step of the search a few bit operations.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use crate::game::{
+    piece_types::{iter_cells, PieceType},
+    playfield::{Cell, Playfield},
+    rotation::Rotation,
+};
+
+/// Perfect clears are searched in this many rows at most.
+pub const MAX_ROWS: usize = 4;
+
+const WIDTH: usize = 10;
+const FULL_ROW: u64 = (1 << WIDTH) - 1;
+
+/// Pieces that fill the highest window, the search never plays more.
+const MAX_PIECES: usize = MAX_ROWS * WIDTH / 4;
+
+/// A way to a perfect clear of the bottom `rows`.
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub rows: usize,
+    pub placements: Vec<Placement>,
+}
+
+/// Where one piece of a solution goes. The rows count from the bottom of the
+/// window before any of them are cleared.
+#[derive(Debug, Clone, Copy)]
+pub struct Placement {
+    pub piece_type: PieceType,
+    pub cells: [IVec2; 4],
+}
+
+fn bit(x: usize, y: usize) -> u64 {
+    1 << (y * WIDTH + x)
+}
+
+/// The bottom rows of the playfield and the height of the stack in them, or
+/// nothing if the playfield is wider than the solver or the stack too high.
+fn window(playfield: &Playfield) -> Option<(u64, usize)> {
+    let size = playfield.size();
+    if size.x as usize != WIDTH {
+        return None;
+    }
+    let mut field = 0;
+    let mut height = 0;
+    for y in 0..size.y as usize {
+        for x in 0..WIDTH {
+            let filled = !matches!(
+                playfield.get(IVec2::new(x as i32, y as i32)),
+                Some(Cell::Empty)
+            );
+            match (filled, y < MAX_ROWS) {
+                (true, true) => {
+                    field |= bit(x, y);
+                    height = y + 1;
+                }
+                (true, false) => return None,
+                _ => {}
+            }
+        }
+    }
+    Some((field, height))
+}
+
+/// The distinct shapes of a piece in all rotations, moved to start at 0, 0.
+fn shapes(piece_type: PieceType) -> Vec<[IVec2; 4]> {
+    let mut shapes: Vec<[IVec2; 4]> = vec![];
+    for rotation in [Rotation::R0, Rotation::R90, Rotation::R180, Rotation::R270] {
+        let mut cells = [IVec2::ZERO; 4];
+        for (cell, position) in cells.iter_mut().zip(iter_cells(piece_type, rotation)) {
+            *cell = position;
+        }
+        let min = cells.iter().fold(IVec2::MAX, |min, cell| min.min(*cell));
+        cells.iter_mut().for_each(|cell| *cell -= min);
+        cells.sort_by_key(|cell| (cell.y, cell.x));
+        if !shapes.contains(&cells) {
+            shapes.push(cells);
+        }
+    }
+    shapes
+}
+
+/// A shape at one position in the window.
+struct Spot {
+    cells: [IVec2; 4],
+    mask: u64,
+    /// The cells above the shape, which have to be empty to drop it in.
+    shadow: u64,
+    /// Rows the window needs to hold the shape.
+    height: usize,
+}
+
+/// Every position of every shape of a piece in the highest window.
+fn spots(piece_type: PieceType) -> Vec<Spot> {
+    let mut spots = vec![];
+    for shape in shapes(piece_type) {
+        for y in 0..MAX_ROWS as i32 {
+            for x in 0..WIDTH as i32 {
+                let cells = shape.map(|cell| cell + IVec2::new(x, y));
+                if cells
+                    .iter()
+                    .any(|cell| cell.x >= WIDTH as i32 || cell.y >= MAX_ROWS as i32)
+                {
+                    continue;
+                }
+                let mut mask = 0;
+                let mut shadow = 0;
+                for cell in cells {
+                    let (x, y) = (cell.x as usize, cell.y as usize);
+                    mask |= bit(x, y);
+                    shadow |= (y + 1..MAX_ROWS).fold(0, |shadow, above| shadow | bit(x, above));
+                }
+                spots.push(Spot {
+                    cells,
+                    mask,
+                    shadow: shadow & !mask,
+                    height: cells
+                        .iter()
+                        .map(|cell| cell.y as usize + 1)
+                        .max()
+                        .unwrap_or(0),
+                });
+            }
+        }
+    }
+    spots
+}
+
+struct Search<'a> {
+    queue: &'a [PieceType],
+    spots: &'a HashMap<PieceType, Vec<Spot>>,
+    /// States that are known to fail, so they are not searched twice.
+    dead_ends: HashSet<(u64, usize, usize, Option<PieceType>)>,
+    solution: Vec<Placement>,
+}
+
+impl Search<'_> {
+    /// `rows` maps the rows that are left to the rows of the window.
+    fn search(&mut self, field: u64, rows: &[usize], next: usize, hold: Option<PieceType>) -> bool {
+        if rows.is_empty() {
+            return true;
+        }
+        let empty = rows.len() * WIDTH - field.count_ones() as usize;
+        let available = self.queue.len() - next + hold.is_some() as usize;
+        if empty / 4 > available {
+            return false;
+        }
+        let state = (field, rows.len(), next, hold);
+        if self.dead_ends.contains(&state) || !regions_fit(field, rows.len()) {
+            return false;
+        }
+
+        // the piece that is played, the hold after it and the next piece
+        let mut choices = [None, None];
+        match (self.queue.get(next), hold) {
+            (Some(&current), hold) => {
+                choices[0] = Some((current, hold, next + 1));
+                choices[1] = match hold {
+                    Some(held) if held != current => Some((held, Some(current), next + 1)),
+                    None => self
+                        .queue
+                        .get(next + 1)
+                        .map(|&after| (after, Some(current), next + 2)),
+                    _ => None,
+                };
+            }
+            (None, Some(held)) => choices[0] = Some((held, None, next)),
+            (None, None) => {}
+        }
+
+        let spots = self.spots;
+        for (piece_type, hold, next) in choices.into_iter().flatten() {
+            for spot in &spots[&piece_type] {
+                if !fits(field, rows.len(), spot) {
+                    continue;
+                }
+                self.solution.push(Placement {
+                    piece_type,
+                    cells: spot
+                        .cells
+                        .map(|cell| IVec2::new(cell.x, rows[cell.y as usize] as i32)),
+                });
+                let found = match clear_rows(field | spot.mask, rows) {
+                    Some((field, rows_left)) => self.search(field, &rows_left, next, hold),
+                    None => self.search(field | spot.mask, rows, next, hold),
+                };
+                if found {
+                    return true;
+                }
+                self.solution.pop();
+            }
+        }
+
+        self.dead_ends.insert(state);
+        false
+    }
+}
+
+/// Whether a piece can drop straight down into a spot and rests there.
+/// Pieces that would have to be slid under an overhang do not count.
+fn fits(field: u64, rows: usize, spot: &Spot) -> bool {
+    spot.height <= rows
+        && field & (spot.mask | spot.shadow) == 0
+        && (spot.mask & FULL_ROW != 0 || field & (spot.mask >> WIDTH) != 0)
+}
+
+/// Removes full rows, the rows above them move down. Nothing if no row is
+/// full.
+fn clear_rows(mut field: u64, rows: &[usize]) -> Option<(u64, Vec<usize>)> {
+    let full = |field: u64, y: usize| (field >> (y * WIDTH)) & FULL_ROW == FULL_ROW;
+    if !(0..rows.len()).any(|y| full(field, y)) {
+        return None;
+    }
+    let mut rows_left = rows.to_vec();
+    for y in (0..rows.len()).rev() {
+        if full(field, y) {
+            let below = field & ((1 << (y * WIDTH)) - 1);
+            let above = field >> ((y + 1) * WIDTH);
+            field = below | (above << (y * WIDTH));
+            rows_left.remove(y);
+        }
+    }
+    Some((field, rows_left))
+}
+
+/// The cells of one column in every row that fits into the bits.
+const fn column(x: usize) -> u64 {
+    let mut column = 0;
+    let mut y = 0;
+    while (y + 1) * WIDTH <= u64::BITS as usize {
+        column |= 1 << (y * WIDTH + x);
+        y += 1;
+    }
+    column
+}
+
+const LEFT_COLUMN: u64 = column(0);
+const RIGHT_COLUMN: u64 = column(WIDTH - 1);
+
+/// Pieces fill four cells, so every enclosed empty region has to be a
+/// multiple of four cells big. Regions grow a step in every direction at
+/// once until they stop changing.
+fn regions_fit(field: u64, rows: usize) -> bool {
+    let mut unvisited = !field & ((1 << (rows * WIDTH)) - 1);
+    while unvisited != 0 {
+        let mut region = unvisited & unvisited.wrapping_neg();
+        loop {
+            let grown = (region
+                | (region << 1) & !LEFT_COLUMN
+                | (region >> 1) & !RIGHT_COLUMN
+                | region << WIDTH
+                | region >> WIDTH)
+                & unvisited;
+            if grown == region {
+                break;
+            }
+            region = grown;
+        }
+        if !region.count_ones().is_multiple_of(4) {
+            return false;
+        }
+        unvisited &= !region;
+    }
+    true
+}
+
+/// Finds pieces that clear the playfield completely, playing `queue` in order
+/// with the help of the hold. Higher windows are tried first, so an empty
+/// playfield is solved like the four row opener.
+pub fn solve(
+    playfield: &Playfield,
+    queue: &[PieceType],
+    hold: Option<PieceType>,
+) -> Option<Solution> {
+    if playfield.is_mirrored() {
+        // reflected pieces fill the mirrored stack the way the usual ones fill
+        // the stack itself
+        let mut unmirrored = playfield.clone();
+        unmirrored.set_mirrored(false);
+        let mut solution = solve(&unmirrored, queue, hold)?;
+        for placement in &mut solution.placements {
+            placement.cells = placement
+                .cells
+                .map(|cell| IVec2::new(WIDTH as i32 - 1 - cell.x, cell.y));
+        }
+        return Some(solution);
+    }
+
+    let (field, height) = window(playfield)?;
+    let filled = field.count_ones() as usize;
+    // one piece more than can be played, it may end up in the hold
+    let queue = &queue[..queue.len().min(MAX_PIECES + 1)];
+    let spots: HashMap<_, _> = queue
+        .iter()
+        .chain(&hold)
+        .unique()
+        .map(|&piece_type| (piece_type, spots(piece_type)))
+        .collect();
+    (height.max(1)..=MAX_ROWS)
+        .rev()
+        .filter(|rows| (rows * WIDTH - filled).is_multiple_of(4))
+        .find_map(|rows| {
+            let mut search = Search {
+                queue,
+                spots: &spots,
+                dead_ends: HashSet::new(),
+                solution: vec![],
+            };
+            let row_map: Vec<_> = (0..rows).collect();
+            search.search(field, &row_map, 0, hold).then_some(Solution {
+                rows,
+                placements: search.solution,
+            })
+        })
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use bevy_prng::ChaCha8Rng;
+use rand_core::SeedableRng;
+
+use crate::game::{
+    piece_order::PieceOrder,
+    piece_types::PieceType::{self, *},
+    playfield::{Cell, Playfield},
+};
+
+use super::solver::{solve, Solution};
+
+fn empty_playfield() -> Playfield {
+    Playfield::new(UVec2::new(10, 24))
+}
+
+/// Every empty cell of the window is filled by exactly one placement.
+fn assert_fills(playfield: &Playfield, solution: &Solution) {
+    let mut filled: Vec<Vec<bool>> = (0..solution.rows as i32)
+        .map(|y| {
+            (0..10)
+                .map(|x| !matches!(playfield.get(IVec2::new(x, y)), Some(Cell::Empty)))
+                .collect()
+        })
+        .collect();
+    for cell in solution
+        .placements
+        .iter()
+        .flat_map(|placement| placement.cells)
+    {
+        let filled = &mut filled[cell.y as usize][cell.x as usize];
+        assert!(!*filled, "{cell} is filled twice");
+        *filled = true;
+    }
+    assert!(filled.iter().flatten().all(|filled| *filled));
+}
+
+#[test]
+fn finds_a_solution_with_o_pieces() {
+    let playfield = empty_playfield();
+    let solution = solve(&playfield, &[O; 10], None).expect("O pieces tile the rows");
+    assert_eq!(solution.rows, 4);
+    assert_eq!(solution.placements.len(), 10);
+    assert_fills(&playfield, &solution);
+}
+
+#[test]
+fn needs_enough_pieces() {
+    // five pieces clear two rows, the least an empty playfield allows
+    assert!(solve(&empty_playfield(), &[O; 4], None).is_none());
+    let solution = solve(&empty_playfield(), &[O; 4], Some(O)).expect("the hold counts");
+    assert_eq!(solution.rows, 2);
+}
+
+#[test]
+fn rejects_regions_pieces_cannot_fill() {
+    let mut playfield = empty_playfield();
+    // leaves a single cell in the bottom left corner
+    playfield.set_cell(IVec2::new(1, 0), Cell::Garbage);
+    playfield.set_cell(IVec2::new(0, 1), Cell::Garbage);
+    assert!(solve(&playfield, &[I; 10], None).is_none());
+}
+
+#[test]
+fn solves_random_bags() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let mut solved = 0;
+    for _ in 0..20 {
+        let order = PieceOrder::known_bags(2, &mut rng);
+        let queue: Vec<PieceType> = order.upcoming().to_vec();
+        let playfield = empty_playfield();
+        if let Some(solution) = solve(&playfield, &queue[..10], None) {
+            assert_eq!(solution.rows, 4);
+            assert_fills(&playfield, &solution);
+            solved += 1;
+        }
+    }
+    // the first perfect clear is possible with almost every start
+    assert!(solved >= 15, "only {solved} of 20 starts solved");
+}
+
+#[test]
+fn searches_only_the_pieces_that_fit() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let order = PieceOrder::known_bags(crate::game::TRAINING_BAGS, &mut rng);
+    let queue = order.upcoming();
+    let playfield = empty_playfield();
+    // the whole training queue is searched like its first pieces
+    assert_eq!(
+        solve(&playfield, queue, None).map(|solution| solution.placements.len()),
+        solve(&playfield, &queue[..11], None).map(|solution| solution.placements.len()),
+    );
+}
@@ -1,29 +1,110 @@
 use bevy::prelude::*;
-use bevy_prng::ChaCha8Rng;
-use bevy_rand::resource::GlobalEntropy;
 use rand_core::RngCore;
 
 use super::piece_types::PieceType;
 
-#[derive(Debug, Resource)]
+/// How the pieces are dealt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) enum Randomizer {
+    /// Every piece once in each bag of seven.
+    #[default]
+    SevenBag,
+    /// A random piece, rolled a second time if it repeats the last one.
+    Nes,
+    /// Only the pieces given up front, in order.
+    Fixed,
+}
+
+#[derive(Debug, Component, Clone)]
 pub(super) struct PieceOrder {
     pieces: Vec<PieceType>,
     index: usize,
+    randomizer: Randomizer,
 }
 
 impl PieceOrder {
-    pub(super) fn new(rng: ResMut<GlobalEntropy<ChaCha8Rng>>) -> Self {
+    pub(super) fn new(rng: &mut impl RngCore) -> Self {
         use PieceType::*;
         let mut pieces = vec![O, J, L, S, T, Z, I];
         fisher_yates_shuffle(&mut pieces, rng);
 
-        Self { pieces, index: 0 }
+        Self {
+            pieces,
+            index: 0,
+            randomizer: Randomizer::SevenBag,
+        }
+    }
+
+    pub(super) fn with_randomizer(randomizer: Randomizer, rng: &mut impl RngCore) -> Self {
+        let mut order = match randomizer {
+            Randomizer::SevenBag => Self::new(rng),
+            Randomizer::Nes | Randomizer::Fixed => Self {
+                pieces: vec![],
+                index: 0,
+                randomizer,
+            },
+        };
+        order.refill(rng);
+        order
+    }
+
+    /// Deals exactly `pieces` and nothing after them.
+    pub(super) fn fixed(pieces: Vec<PieceType>) -> Self {
+        Self {
+            pieces,
+            index: 0,
+            randomizer: Randomizer::Fixed,
+        }
+    }
+
+    /// Deals `count` seven-bags that are all known from the start.
+    pub(super) fn known_bags(count: usize, rng: &mut impl RngCore) -> Self {
+        let pieces = (0..count).flat_map(|_| Self::new(rng).pieces).collect();
+        Self::fixed(pieces)
     }
 
     pub(super) fn is_finished(&self) -> bool {
         self.index >= self.pieces.len()
     }
 
+    /// Deals new pieces when the current ones run out. The NES randomizer
+    /// stays one piece ahead, so the next piece is always known.
+    pub(super) fn refill(&mut self, rng: &mut impl RngCore) {
+        match self.randomizer {
+            Randomizer::SevenBag => {
+                if self.is_finished() {
+                    *self = Self::new(rng);
+                }
+            }
+            Randomizer::Nes => {
+                self.pieces.drain(..self.index.min(self.pieces.len()));
+                self.index = 0;
+                while self.pieces.len() < 2 {
+                    let piece = nes_roll(self.pieces.last().copied(), rng);
+                    self.pieces.push(piece);
+                }
+            }
+            Randomizer::Fixed => {}
+        }
+    }
+
+    /// The pieces of the current bag that were not handed out yet.
+    pub(super) fn upcoming(&self) -> &[PieceType] {
+        &self.pieces[self.index.min(self.pieces.len())..]
+    }
+
+    /// Replaces the pieces that were not handed out yet.
+    pub(super) fn set_upcoming(&mut self, pieces: Vec<PieceType>) {
+        self.pieces = pieces;
+        self.index = 0;
+    }
+
+    /// Deals `piece` before all others.
+    pub(super) fn push_next(&mut self, piece: PieceType) {
+        let index = self.index.min(self.pieces.len());
+        self.pieces.insert(index, piece);
+    }
+
     pub(super) fn next_piece(&mut self) -> Option<PieceType> {
         let result = match self.index {
             index if index < self.pieces.len() => Some(self.pieces[index]),
@@ -36,13 +117,21 @@ impl PieceOrder {
     }
 }
 
-pub(super) fn create_piece_order(mut commands: Commands, rng: ResMut<GlobalEntropy<ChaCha8Rng>>) {
-    commands.insert_resource(PieceOrder::new(rng));
-}
-
-fn fisher_yates_shuffle<T>(items: &mut [T], mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>) {
+fn fisher_yates_shuffle<T>(items: &mut [T], rng: &mut impl RngCore) {
     for i in (1..items.len()).rev() {
         let j = rng.next_u32() as usize % i;
         items.swap(i, j);
     }
 }
+
+/// Rolls one of eight outcomes, where the eighth and a repeat of the last
+/// piece roll again among the seven pieces, like the NES does.
+fn nes_roll(last: Option<PieceType>, rng: &mut impl RngCore) -> PieceType {
+    use PieceType::*;
+    const PIECES: [PieceType; 7] = [T, J, Z, O, S, L, I];
+
+    match PIECES.get(rng.next_u32() as usize % 8) {
+        Some(&piece) if Some(piece) != last => piece,
+        _ => PIECES[rng.next_u32() as usize % 7],
+    }
+}
@@ -1,8 +1,9 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use super::{Piece, Rotation};
 
-#[derive(Reflect, PartialEq, Eq, Debug, Copy, Clone)]
+#[derive(Reflect, PartialEq, Eq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum PieceType {
     O,
     J,
@@ -73,22 +74,44 @@ pub fn iter_cells(piece_type: PieceType, rotation: Rotation) -> impl Iterator<It
         PieceType::I => &I_CELLS,
     };
 
-    cells.iter().map(move |c| match rotation {
-        Rotation::R0 => *c,
-        Rotation::R90 => IVec2 { x: -c.y, y: c.x },
-        Rotation::R180 => IVec2 { x: -c.x, y: -c.y },
-        Rotation::R270 => IVec2 { x: c.y, y: -c.x },
-    })
+    cells.iter().map(move |c| rotation.rotate(*c))
+}
+
+/// Reflects an offset from the center of a piece left to right if the piece
+/// is mirrored.
+pub fn reflect(offset: IVec2, mirrored: bool) -> IVec2 {
+    if mirrored {
+        IVec2::new(-offset.x, offset.y)
+    } else {
+        offset
+    }
+}
+
+/// The cells of a piece, reflected left to right on a mirrored board, which
+/// turns J into L and S into Z shapes.
+pub fn iter_shape(
+    piece_type: PieceType,
+    rotation: Rotation,
+    mirrored: bool,
+) -> impl Iterator<Item = IVec2> {
+    iter_cells(piece_type, rotation).map(move |c| reflect(c, mirrored))
 }
 
+/// The cells the piece covers on the playfield. Scaled pieces cover a square
+/// of cells for every mino.
 pub fn iter_piece_cells(
     Piece {
         position,
         rotation,
         piece_type,
+        scale,
+        mirrored,
+        ..
     }: &Piece,
 ) -> impl Iterator<Item = IVec2> + '_ {
-    iter_cells(*piece_type, *rotation).map(move |c| *position + c)
+    iter_shape(*piece_type, *rotation, *mirrored).flat_map(move |c| {
+        (0..scale * scale).map(move |i| *position + c * *scale + IVec2::new(i % scale, i / scale))
+    })
 }
 
 pub const EMPTY_SPRITE: TextureAtlasSprite = TextureAtlasSprite {
@@ -100,6 +123,42 @@ pub const EMPTY_SPRITE: TextureAtlasSprite = TextureAtlasSprite {
     anchor: bevy::sprite::Anchor::Center,
 };
 
+pub const GARBAGE_SPRITE: TextureAtlasSprite = TextureAtlasSprite {
+    color: Color::GRAY,
+    index: 7,
+    flip_x: false,
+    flip_y: false,
+    custom_size: None,
+    anchor: bevy::sprite::Anchor::Center,
+};
+
+/// The usual letter of the piece, as used by bots and spectators.
+pub fn piece_letter(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::O => 'O',
+        PieceType::J => 'J',
+        PieceType::L => 'L',
+        PieceType::S => 'S',
+        PieceType::T => 'T',
+        PieceType::Z => 'Z',
+        PieceType::I => 'I',
+    }
+}
+
+/// The piece with the given letter, the inverse of [`piece_letter`].
+pub fn piece_from_letter(letter: char) -> Option<PieceType> {
+    match letter {
+        'O' => Some(PieceType::O),
+        'J' => Some(PieceType::J),
+        'L' => Some(PieceType::L),
+        'S' => Some(PieceType::S),
+        'T' => Some(PieceType::T),
+        'Z' => Some(PieceType::Z),
+        'I' => Some(PieceType::I),
+        _ => None,
+    }
+}
+
 pub fn get_sprite_for_piece(piece_type: PieceType) -> TextureAtlasSprite {
     let (color, index) = match piece_type {
         PieceType::O => (BRIGHT_ORANGE, 1),
@@ -1,22 +1,57 @@
+use std::{ops::Range, time::Duration};
+
 use bevy::prelude::*;
 
 use itertools::{Itertools, MinMaxResult};
+use rand_core::RngCore;
+use serde::Serialize;
+
+use super::{
+    items::Item,
+    piece_types::{iter_piece_cells, piece_letter, reflect},
+    Piece, PieceType,
+};
 
-use super::{piece_types::iter_piece_cells, Piece, PieceType};
+#[cfg(test)]
+mod tests;
+
+/// Corners around the center of a T piece, the first two are on the side its
+/// nub points to.
+const T_CORNERS: [IVec2; 4] = [
+    IVec2::new(-1, 1),
+    IVec2::new(1, 1),
+    IVec2::new(-1, -1),
+    IVec2::new(1, -1),
+];
 
 #[derive(Resource)]
 pub struct PlayfieldSize(pub UVec2);
 
-#[derive(Component)]
+#[derive(Component, Debug, Clone)]
 pub struct Playfield {
     size: UVec2,
     cells: Vec<Row>,
+    /// How long the playfield has been played on, cells remember it when
+    /// they are filled.
+    age: Duration,
+    /// Number of pieces placed, cells remember which one filled them.
+    locks: u32,
+    clear_rule: ClearRule,
+    /// Whether the board is played mirrored left to right, its pieces spawn
+    /// reflected.
+    mirrored: bool,
+    /// Items in cleared lines, with where their line ended up.
+    triggered_items: Vec<(IVec2, Item)>,
 }
 
 #[derive(Debug, Clone, Default)]
 struct Row {
     cells: Vec<Cell>,
     filled: usize,
+    /// The age of the playfield when each cell was filled.
+    filled_at: Vec<Duration>,
+    /// The lock that filled each cell, 0 for garbage.
+    filled_by: Vec<u32>,
 }
 
 impl Row {
@@ -24,6 +59,30 @@ impl Row {
         Self {
             cells: vec![Cell::Empty; width],
             filled: 0,
+            filled_at: vec![Duration::ZERO; width],
+            filled_by: vec![0; width],
+        }
+    }
+}
+
+/// What happens to the blocks above cleared rows, chosen in the menu.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClearRule {
+    /// Everything above moves down by the cleared rows.
+    #[default]
+    Naive,
+    /// Blocks of each piece fall as a group until they land, which can clear
+    /// more rows in a chain.
+    Cascade,
+}
+
+impl ClearRule {
+    pub const ALL: [ClearRule; 2] = [ClearRule::Naive, ClearRule::Cascade];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ClearRule::Naive => "Naive gravity",
+            ClearRule::Cascade => "Cascade gravity",
         }
     }
 }
@@ -33,6 +92,78 @@ pub enum Cell {
     #[default]
     Empty,
     Filled(PieceType),
+    /// A block of a piece that carries an item.
+    Item(PieceType, Item),
+    Garbage,
+}
+
+impl Cell {
+    /// Fixed bytes for the cell, the kind followed by its piece and item.
+    fn bytes(self) -> [u8; 3] {
+        let letter = |piece_type| piece_letter(piece_type) as u8;
+        match self {
+            Cell::Empty => [0, 0, 0],
+            Cell::Filled(piece_type) => [1, letter(piece_type), 0],
+            Cell::Item(piece_type, item) => {
+                let item = match item {
+                    Item::Bomb => 1,
+                    Item::Laser => 2,
+                    Item::Flip => 3,
+                    Item::SlowGravity => 4,
+                };
+                [2, letter(piece_type), item]
+            }
+            Cell::Garbage => [3, 0, 0],
+        }
+    }
+}
+
+/// How the holes of garbage rows that are added at once line up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GarbagePattern {
+    /// All rows have their hole in the same column.
+    Clean { hole_column: usize },
+    /// The hole moves to a random column with the given chance per row.
+    Messy { change_chance: f32 },
+    /// Every row has its hole in a different column than the row below.
+    Cheese,
+}
+
+impl GarbagePattern {
+    /// The hole column of the next row, `previous` is the hole of the row
+    /// below if it belongs to the same pattern.
+    pub fn next_hole(self, previous: Option<usize>, width: usize, rng: &mut impl RngCore) -> usize {
+        let random_column = |rng: &mut dyn RngCore| rng.next_u32() as usize % width;
+
+        match (self, previous) {
+            (GarbagePattern::Clean { hole_column }, _) => hole_column % width,
+            (_, None) => random_column(rng),
+            (GarbagePattern::Messy { change_chance }, Some(previous)) => {
+                let roll = rng.next_u32() as f32 / u32::MAX as f32;
+                if roll < change_chance {
+                    random_column(rng)
+                } else {
+                    previous
+                }
+            }
+            // a single column leaves no other column to move to
+            (GarbagePattern::Cheese, Some(_)) if width <= 1 => random_column(rng),
+            // never twice in the same column, so every row is its own piece
+            // of cheese
+            (GarbagePattern::Cheese, Some(previous)) => {
+                (previous + 1 + rng.next_u32() as usize % (width - 1)) % width
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TSpin {
+    #[default]
+    None,
+    Mini,
+    Full,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -43,13 +174,51 @@ pub enum CheckRotationResult {
 
 impl Playfield {
     pub fn new(size: UVec2) -> Self {
-        let row = Row {
-            cells: vec![Cell::Empty; size.x as usize],
-            filled: 0,
-        };
+        let row = Row::new(size.x as usize);
         let cells = (0..size.y).map(|_| row.clone()).collect::<Vec<_>>();
 
-        Self { size, cells }
+        Self {
+            size,
+            cells,
+            age: Duration::ZERO,
+            locks: 0,
+            clear_rule: ClearRule::Naive,
+            mirrored: false,
+            triggered_items: vec![],
+        }
+    }
+
+    pub fn clear_rule(&self) -> ClearRule {
+        self.clear_rule
+    }
+
+    pub fn set_clear_rule(&mut self, clear_rule: ClearRule) {
+        self.clear_rule = clear_rule;
+    }
+
+    pub fn is_mirrored(&self) -> bool {
+        self.mirrored
+    }
+
+    /// Plays the board mirrored or not. The stack is mirrored along when this
+    /// changes, so it stays the same as seen from the other side.
+    pub fn set_mirrored(&mut self, mirrored: bool) {
+        if self.mirrored != mirrored {
+            self.mirrored = mirrored;
+            self.flip();
+        }
+    }
+
+    /// Lets time pass for the cells.
+    pub fn tick(&mut self, delta: Duration) {
+        self.age += delta;
+    }
+
+    /// How long ago the cell was filled.
+    pub fn time_since_filled(&self, IVec2 { x, y }: IVec2) -> Option<Duration> {
+        let row = self.cells.get(usize::try_from(y).ok()?)?;
+        let filled_at = row.filled_at.get(usize::try_from(x).ok()?)?;
+        Some(self.age.saturating_sub(*filled_at))
     }
 
     pub fn get(&self, coordinate: IVec2) -> Option<&Cell> {
@@ -70,6 +239,10 @@ impl Playfield {
             .and_then(|row| row.cells.get_mut(coordinate.x as usize))
     }
 
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
     fn valid_coordinate(&self, IVec2 { x, y }: IVec2) -> bool {
         x >= 0 && y >= 0 && x < self.size.x as i32 && y < self.size.y as i32
     }
@@ -79,6 +252,15 @@ impl Playfield {
             .filter(|y| self.cells[*y as usize].filled == self.size.x as usize)
             .collect();
 
+        for (below, row) in cleared_rows.iter().enumerate() {
+            for (x, cell) in self.cells[*row as usize].cells.iter().enumerate() {
+                if let Cell::Item(_, item) = cell {
+                    let position = IVec2::new(x as i32, *row as i32 - below as i32);
+                    self.triggered_items.push((position, *item));
+                }
+            }
+        }
+
         cleared_rows.iter().rev().for_each(|row| {
             self.cells.remove(*row as usize);
         });
@@ -88,6 +270,123 @@ impl Playfield {
         cleared_rows.len()
     }
 
+    /// Takes the items of the lines cleared since the last call.
+    pub fn take_triggered_items(&mut self) -> Vec<(IVec2, Item)> {
+        std::mem::take(&mut self.triggered_items)
+    }
+
+    /// Puts a cell in place, as if garbage filled it.
+    pub fn set_cell(&mut self, coordinate: IVec2, cell: Cell) {
+        self.clear_cell(coordinate);
+        if matches!(cell, Cell::Empty) {
+            return;
+        }
+        let age = self.age;
+        let Some(target) = self.get_mut(coordinate) else {
+            return;
+        };
+        *target = cell;
+        let row = &mut self.cells[coordinate.y as usize];
+        row.filled += 1;
+        row.filled_at[coordinate.x as usize] = age;
+        row.filled_by[coordinate.x as usize] = 0;
+    }
+
+    /// Fills every empty cell beside the `well` columns in the bottom
+    /// `height` rows with garbage.
+    ///
+    /// Unlike [`Playfield::push_garbage_row`] nothing moves: rows pushed in
+    /// from below would lift the stack in the well along with them, while
+    /// walls that clears wore down only need their top rows back.
+    pub fn fill_walls(&mut self, well: Range<usize>, height: usize) {
+        for y in 0..height.min(self.cells.len()) {
+            for x in (0..self.size.x as usize).filter(|x| !well.contains(x)) {
+                let coordinate = IVec2::new(x as i32, y as i32);
+                if matches!(self.get(coordinate), Some(Cell::Empty)) {
+                    self.set_cell(coordinate, Cell::Garbage);
+                }
+            }
+        }
+    }
+
+    /// Empties a single cell, the rows above stay where they are.
+    pub fn clear_cell(&mut self, coordinate: IVec2) {
+        let Some(cell) = self.get_mut(coordinate) else {
+            return;
+        };
+        if matches!(cell, Cell::Empty) {
+            return;
+        }
+        *cell = Cell::Empty;
+        self.cells[coordinate.y as usize].filled -= 1;
+    }
+
+    /// Mirrors the stack left to right.
+    pub fn flip(&mut self) {
+        for row in self.cells.iter_mut() {
+            row.cells.reverse();
+            row.filled_at.reverse();
+            row.filled_by.reverse();
+        }
+    }
+
+    /// Number of rows with garbage in them.
+    pub fn garbage_rows(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|row| row.cells.iter().any(|cell| matches!(cell, Cell::Garbage)))
+            .count()
+    }
+
+    /// Empties the given number of rows at the top.
+    pub fn clear_top_rows(&mut self, rows: usize) {
+        let width = self.size.x as usize;
+        let start = self.cells.len().saturating_sub(rows);
+        self.cells[start..].fill_with(|| Row::new(width));
+    }
+
+    /// Pushes garbage rows in from the bottom and shifts the stack up. The
+    /// holes are placed according to `pattern`.
+    ///
+    /// Returns whether blocks were pushed out at the top, which counts as a
+    /// top out.
+    pub fn add_garbage(
+        &mut self,
+        rows: usize,
+        pattern: GarbagePattern,
+        rng: &mut impl RngCore,
+    ) -> bool {
+        let width = self.size.x as usize;
+        let mut hole_column = None;
+        let mut topped_out = false;
+
+        for _ in 0..rows {
+            let column = pattern.next_hole(hole_column, width, rng);
+            topped_out |= self.push_garbage_row(column);
+            hole_column = Some(column);
+        }
+
+        topped_out
+    }
+
+    /// Inserts a single garbage row at the bottom. Returns whether the row
+    /// pushed out at the top had blocks in it.
+    pub fn push_garbage_row(&mut self, hole_column: usize) -> bool {
+        let width = self.size.x as usize;
+        let mut row = Row {
+            cells: vec![Cell::Garbage; width],
+            filled: width - 1,
+            filled_at: vec![self.age; width],
+            filled_by: vec![0; width],
+        };
+        row.cells[hole_column] = Cell::Empty;
+
+        let pushed_out = self.cells.pop().expect("playfield has rows");
+        self.cells.insert(0, row);
+
+        pushed_out.filled > 0
+    }
+
     pub fn check_move(&self, piece: &Piece) -> bool {
         let all_free = iter_piece_cells(piece).all(|p| {
             let cell = self.get(p);
@@ -118,9 +417,8 @@ impl Playfield {
         let after_wall_pos = piece.position + offset;
 
         let valid_pos = self.check_move(&Piece {
-            piece_type: piece.piece_type,
             position: after_wall_pos,
-            rotation: piece.rotation,
+            ..*piece
         });
 
         if valid_pos {
@@ -130,13 +428,185 @@ impl Playfield {
         }
     }
 
+    /// Uses the three corner rule: a T piece that was rotated into place with
+    /// three occupied corners is a T-spin, a mini one if only one of the
+    /// corners next to its nub is occupied.
+    pub fn t_spin(&self, piece: &Piece) -> TSpin {
+        if piece.piece_type != PieceType::T || !piece.rotated_last {
+            return TSpin::None;
+        }
+
+        let occupied = T_CORNERS.map(|corner| {
+            let corner = reflect(piece.rotation.rotate(corner), piece.mirrored);
+            let cell = self.get(piece.position + corner * piece.scale);
+            !matches!(cell, Some(Cell::Empty))
+        });
+
+        let front = occupied[..2].iter().filter(|o| **o).count();
+        let back = occupied[2..].iter().filter(|o| **o).count();
+
+        match (front, back) {
+            (2, 1..) => TSpin::Full,
+            (1, 2) => TSpin::Mini,
+            _ => TSpin::None,
+        }
+    }
+
+    /// Hash of all cells, used to check that two game instances see the
+    /// same board. Only the cells count, the times they were filled at differ
+    /// between the boards of an online match.
+    ///
+    /// The hash is sent to the peer, so it is a plain FNV-1a over fixed bytes
+    /// that comes out the same on every build.
+    pub fn state_hash(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        let size = self.size.to_array().map(u32::to_le_bytes);
+        let cells = self
+            .cells
+            .iter()
+            .flat_map(|row| row.cells.iter())
+            .flat_map(|cell| cell.bytes());
+        size.into_iter()
+            .flatten()
+            .chain(cells)
+            .fold(OFFSET_BASIS, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(PRIME)
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(|row| row.filled == 0)
+    }
+
     pub fn set_cells(&mut self, piece: &Piece) {
-        iter_piece_cells(piece).for_each(|p| {
+        self.locks += 1;
+        iter_piece_cells(piece).enumerate().for_each(|(i, p)| {
             let cell = self.get_mut(p);
             if let Some(cell) = cell {
-                *cell = Cell::Filled(piece.piece_type);
-                self.cells[p.y as usize].filled += 1;
+                // the item sits on the first mino
+                *cell = match piece.item {
+                    Some(item) if i == 0 => Cell::Item(piece.piece_type, item),
+                    _ => Cell::Filled(piece.piece_type),
+                };
+                let row = &mut self.cells[p.y as usize];
+                row.filled += 1;
+                row.filled_at[p.x as usize] = self.age;
+                row.filled_by[p.x as usize] = self.locks;
             }
         });
     }
+
+    /// Clears full rows according to the clear rule. Returns the rows
+    /// cleared by each step of the chain, without cascade gravity there is
+    /// at most one step.
+    pub fn clear_chain(&mut self) -> Vec<usize> {
+        let mut chain = vec![];
+        loop {
+            let cleared_rows = self.clear_rows();
+            if cleared_rows == 0 {
+                break;
+            }
+            chain.push(cleared_rows);
+            if self.clear_rule == ClearRule::Naive || !self.settle() {
+                break;
+            }
+        }
+        chain
+    }
+
+    /// Lets every group of connected cells that were filled by the same lock
+    /// fall until it lands. Returns whether anything fell.
+    fn settle(&mut self) -> bool {
+        let mut fell = false;
+        loop {
+            let mut groups = self.groups();
+            groups.sort_by_key(|group| group.iter().map(|cell| cell.y).min());
+
+            let mut moved = false;
+            for mut group in groups {
+                while self.can_fall(&group) {
+                    self.move_down(&mut group);
+                    moved = true;
+                }
+            }
+            if !moved {
+                return fell;
+            }
+            fell = true;
+        }
+    }
+
+    /// Filled cells split into groups that are connected and came from the
+    /// same lock.
+    fn groups(&self) -> Vec<Vec<IVec2>> {
+        let size = self.size.as_ivec2();
+        let mut seen = vec![false; (size.x * size.y) as usize];
+        let index = |p: IVec2| (p.y * size.x + p.x) as usize;
+        let mut groups = vec![];
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let start = IVec2::new(x, y);
+                if seen[index(start)] || matches!(self.get(start), Some(Cell::Empty)) {
+                    continue;
+                }
+                let origin = self.filled_by(start);
+                seen[index(start)] = true;
+                let mut group = vec![];
+                let mut open = vec![start];
+                while let Some(p) = open.pop() {
+                    group.push(p);
+                    for next in [p + IVec2::X, p - IVec2::X, p + IVec2::Y, p - IVec2::Y] {
+                        let connected = matches!(self.get(next), Some(cell) if !matches!(cell, Cell::Empty))
+                            && self.filled_by(next) == origin;
+                        if connected && !seen[index(next)] {
+                            seen[index(next)] = true;
+                            open.push(next);
+                        }
+                    }
+                }
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
+    fn filled_by(&self, IVec2 { x, y }: IVec2) -> u32 {
+        self.cells[y as usize].filled_by[x as usize]
+    }
+
+    fn can_fall(&self, group: &[IVec2]) -> bool {
+        group.iter().all(|cell| {
+            let below = *cell - IVec2::Y;
+            group.contains(&below) || matches!(self.get(below), Some(Cell::Empty))
+        })
+    }
+
+    fn move_down(&mut self, group: &mut [IVec2]) {
+        let taken: Vec<_> = group
+            .iter()
+            .map(|p| {
+                let row = &mut self.cells[p.y as usize];
+                let x = p.x as usize;
+                row.filled -= 1;
+                (
+                    std::mem::take(&mut row.cells[x]),
+                    row.filled_at[x],
+                    row.filled_by[x],
+                )
+            })
+            .collect();
+
+        for (p, (cell, filled_at, filled_by)) in group.iter_mut().zip(taken) {
+            *p -= IVec2::Y;
+            let row = &mut self.cells[p.y as usize];
+            let x = p.x as usize;
+            row.cells[x] = cell;
+            row.filled += 1;
+            row.filled_at[x] = filled_at;
+            row.filled_by[x] = filled_by;
+        }
+    }
 }
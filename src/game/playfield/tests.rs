@@ -0,0 +1,367 @@
+use bevy::prelude::*;
+use bevy_prng::ChaCha8Rng;
+use rand_core::SeedableRng;
+
+use super::{Cell, GarbagePattern, Playfield, TSpin};
+use crate::game::{
+    piece_types::{iter_piece_cells, PieceType},
+    rotation::Rotation,
+    Piece,
+};
+
+const WIDTH: usize = 10;
+
+fn playfield() -> Playfield {
+    Playfield::new(UVec2::new(WIDTH as u32, 8))
+}
+
+fn rng() -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(3)
+}
+
+fn is_empty(playfield: &Playfield, x: usize, y: usize) -> bool {
+    matches!(
+        playfield.get(IVec2::new(x as i32, y as i32)),
+        Some(Cell::Empty)
+    )
+}
+
+/// The hole of every garbage row from the bottom up, checking that each row
+/// has exactly one.
+fn holes(playfield: &Playfield, rows: usize) -> Vec<usize> {
+    (0..rows)
+        .map(|y| {
+            let holes: Vec<_> = (0..WIDTH).filter(|&x| is_empty(playfield, x, y)).collect();
+            assert_eq!(holes.len(), 1, "row {y} has holes {holes:?}");
+            holes[0]
+        })
+        .collect()
+}
+
+/// The counter of every row matches its cells.
+fn assert_filled_counts(playfield: &Playfield) {
+    for (y, row) in playfield.cells.iter().enumerate() {
+        let filled = row
+            .cells
+            .iter()
+            .filter(|cell| !matches!(cell, Cell::Empty))
+            .count();
+        assert_eq!(row.filled, filled, "row {y}");
+    }
+}
+
+#[test]
+fn clean_garbage_lines_up() {
+    let mut playfield = playfield();
+    let pattern = GarbagePattern::Clean { hole_column: 2 };
+    assert!(!playfield.add_garbage(4, pattern, &mut rng()));
+    assert_eq!(holes(&playfield, 4), vec![2; 4]);
+    assert_eq!(playfield.garbage_rows(), 4);
+    assert_filled_counts(&playfield);
+}
+
+#[test]
+fn messy_garbage_moves_by_chance() {
+    let mut playfield = playfield();
+    let pattern = GarbagePattern::Messy { change_chance: 0.0 };
+    playfield.add_garbage(6, pattern, &mut rng());
+    let steady = holes(&playfield, 6);
+    assert!(steady.iter().all(|hole| *hole == steady[0]), "{steady:?}");
+
+    let mut playfield = Playfield::new(UVec2::new(WIDTH as u32, 8));
+    let pattern = GarbagePattern::Messy { change_chance: 1.0 };
+    playfield.add_garbage(6, pattern, &mut rng());
+    let moving = holes(&playfield, 6);
+    assert!(
+        moving.windows(2).any(|pair| pair[0] != pair[1]),
+        "{moving:?}"
+    );
+}
+
+#[test]
+fn cheese_garbage_never_repeats_a_column() {
+    let mut playfield = playfield();
+    playfield.add_garbage(8, GarbagePattern::Cheese, &mut rng());
+    let cheese = holes(&playfield, 8);
+    assert!(
+        cheese.windows(2).all(|pair| pair[0] != pair[1]),
+        "{cheese:?}"
+    );
+}
+
+#[test]
+fn cheese_garbage_fits_a_single_column() {
+    let mut playfield = Playfield::new(UVec2::new(1, 4));
+    playfield.add_garbage(3, GarbagePattern::Cheese, &mut rng());
+    assert!((0..3).all(|y| is_empty(&playfield, 0, y)));
+}
+
+#[test]
+fn garbage_pushes_the_stack_up() {
+    let mut playfield = playfield();
+    playfield.set_cell(IVec2::new(4, 0), Cell::Filled(PieceType::T));
+    playfield.set_cell(IVec2::new(5, 0), Cell::Filled(PieceType::T));
+
+    assert!(!playfield.push_garbage_row(0));
+    assert!(!is_empty(&playfield, 4, 1));
+    assert!(!is_empty(&playfield, 5, 1));
+    assert!(is_empty(&playfield, 4, 2));
+    assert_eq!(playfield.cells[1].filled, 2);
+    assert_eq!(playfield.cells[0].filled, WIDTH - 1);
+    assert_filled_counts(&playfield);
+}
+
+#[test]
+fn garbage_tops_out_when_blocks_leave_the_top() {
+    let mut playfield = playfield();
+    let top = playfield.size().y as i32 - 1;
+    playfield.set_cell(IVec2::new(3, top - 1), Cell::Garbage);
+
+    // the top row is still empty the first time
+    assert!(!playfield.push_garbage_row(0));
+    assert!(playfield.push_garbage_row(0));
+
+    let mut playfield = Playfield::new(UVec2::new(WIDTH as u32, 8));
+    playfield.set_cell(IVec2::new(3, top - 2), Cell::Garbage);
+    let pattern = GarbagePattern::Clean { hole_column: 0 };
+    assert!(playfield.add_garbage(3, pattern, &mut rng()));
+    assert_filled_counts(&playfield);
+}
+
+#[test]
+fn state_hash_is_fixed() {
+    let mut playfield = playfield();
+    // FNV-1a of the size and the empty cells never changes between builds
+    let empty = playfield.state_hash();
+    assert_eq!(empty, 0x6082_b29e_23d9_7cc7);
+    playfield.set_cell(IVec2::new(0, 0), Cell::Filled(PieceType::T));
+    assert_ne!(playfield.state_hash(), empty);
+    playfield.set_cell(IVec2::new(0, 0), Cell::Garbage);
+    let garbage = playfield.state_hash();
+    playfield.set_cell(IVec2::new(0, 0), Cell::Filled(PieceType::T));
+    assert_ne!(playfield.state_hash(), garbage);
+}
+
+/// A 4 wide playfield on cascade gravity.
+fn cascade_playfield() -> Playfield {
+    let mut playfield = Playfield::new(UVec2::new(4, 6));
+    playfield.set_clear_rule(super::ClearRule::Cascade);
+    playfield
+}
+
+/// Fills cells as if they were placed by lock number `lock`, 0 is garbage.
+fn fill(playfield: &mut Playfield, lock: u32, cells: &[(usize, usize)]) {
+    for &(x, y) in cells {
+        let row = &mut playfield.cells[y];
+        row.cells[x] = match lock {
+            0 => Cell::Garbage,
+            _ => Cell::Filled(PieceType::S),
+        };
+        row.filled += 1;
+        row.filled_by[x] = lock;
+    }
+}
+
+#[test]
+fn split_piece_falls_as_separate_groups() {
+    let mut playfield = cascade_playfield();
+    // an upright S with its middle row cleared leaves two diagonal halves
+    fill(&mut playfield, 1, &[(0, 2), (0, 1), (1, 1), (1, 0)]);
+    fill(&mut playfield, 2, &[(2, 1), (3, 1)]);
+
+    assert_eq!(playfield.clear_chain(), vec![1]);
+    // the upper half falls into the gap beside the lower half
+    assert!(!is_empty(&playfield, 0, 0));
+    assert!(!is_empty(&playfield, 1, 0));
+    assert!(is_empty(&playfield, 0, 1));
+    assert_filled_counts(&playfield);
+}
+
+#[test]
+fn falling_group_clears_another_row() {
+    let mut playfield = cascade_playfield();
+    fill(&mut playfield, 0, &[(0, 0), (1, 0), (2, 0)]);
+    fill(&mut playfield, 1, &[(0, 1), (1, 1), (2, 1), (3, 1)]);
+    fill(&mut playfield, 2, &[(3, 2), (3, 3)]);
+
+    // the bar falls into the hole of the garbage row and clears it
+    assert_eq!(playfield.clear_chain(), vec![1, 1]);
+    assert!(!is_empty(&playfield, 3, 0));
+    assert!((0..3).all(|x| is_empty(&playfield, x, 0)));
+    assert!(is_empty(&playfield, 3, 1));
+    assert_filled_counts(&playfield);
+
+    // naive gravity stops after the first clear
+    let mut playfield = cascade_playfield();
+    playfield.set_clear_rule(super::ClearRule::Naive);
+    fill(&mut playfield, 0, &[(0, 0), (1, 0), (2, 0)]);
+    fill(&mut playfield, 1, &[(0, 1), (1, 1), (2, 1), (3, 1)]);
+    fill(&mut playfield, 2, &[(3, 2), (3, 3)]);
+    assert_eq!(playfield.clear_chain(), vec![1]);
+}
+
+#[test]
+fn garbage_holds_together() {
+    let mut playfield = cascade_playfield();
+    // an overhang of garbage that only stands as one piece
+    fill(&mut playfield, 0, &[(1, 0), (0, 1), (1, 1)]);
+    fill(&mut playfield, 1, &[(0, 3), (1, 3), (2, 3), (3, 3)]);
+
+    assert_eq!(playfield.clear_chain(), vec![1]);
+    assert!(is_empty(&playfield, 0, 0));
+    assert!(!is_empty(&playfield, 0, 1));
+    assert!(!is_empty(&playfield, 1, 1));
+    assert_filled_counts(&playfield);
+}
+
+#[test]
+fn walls_fill_beside_the_well() {
+    let mut playfield = playfield();
+    playfield.set_cell(IVec2::new(4, 0), Cell::Filled(PieceType::T));
+    playfield.fill_walls(3..7, 5);
+
+    for y in 0..8 {
+        for x in 0..WIDTH {
+            let wall = y < 5 && !(3..7).contains(&x);
+            let residue = (x, y) == (4, 0);
+            assert_eq!(!is_empty(&playfield, x, y), wall || residue, "{x}, {y}");
+        }
+    }
+    // the stack in the well is kept as it was
+    assert!(matches!(
+        playfield.get(IVec2::new(4, 0)),
+        Some(Cell::Filled(PieceType::T))
+    ));
+    assert_filled_counts(&playfield);
+}
+
+#[test]
+fn walls_grow_back_after_clears() {
+    let mut playfield = playfield();
+    playfield.fill_walls(3..7, 5);
+    for x in 3..7 {
+        playfield.set_cell(IVec2::new(x, 0), Cell::Filled(PieceType::I));
+    }
+    assert_eq!(playfield.clear_chain(), vec![1]);
+    assert!(is_empty(&playfield, 0, 4));
+
+    playfield.fill_walls(3..7, 5);
+    assert!(!is_empty(&playfield, 0, 4));
+    assert!(is_empty(&playfield, 0, 5));
+    assert_eq!(holes_in_well(&playfield), 4 * 5);
+    assert_filled_counts(&playfield);
+}
+
+fn holes_in_well(playfield: &Playfield) -> usize {
+    (0..5)
+        .flat_map(|y| (3..7).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_empty(playfield, x, y))
+        .count()
+}
+
+/// The cells of every row from the bottom up.
+fn stack(playfield: &Playfield) -> Vec<Vec<[u8; 3]>> {
+    playfield
+        .cells
+        .iter()
+        .map(|row| row.cells.iter().map(|cell| cell.bytes()).collect())
+        .collect()
+}
+
+fn mirror_image(playfield: &Playfield) -> Vec<Vec<[u8; 3]>> {
+    let mut flipped = playfield.clone();
+    flipped.flip();
+    stack(&flipped)
+}
+
+/// Drops a piece straight down and locks it. Returns its T-spin and the rows
+/// of every clear step.
+fn drop_piece(playfield: &mut Playfield, mut piece: Piece) -> (TSpin, Vec<usize>) {
+    assert!(playfield.check_move(&piece), "{piece:?}");
+    while playfield.check_move(&Piece {
+        position: piece.position - IVec2::Y,
+        ..piece
+    }) {
+        piece.position -= IVec2::Y;
+    }
+    let t_spin = playfield.t_spin(&piece);
+    playfield.set_cells(&piece);
+    (t_spin, playfield.clear_chain())
+}
+
+#[test]
+fn mirrored_placements_give_the_mirrored_board() {
+    use PieceType::*;
+    let placements = [
+        (I, Rotation::R0, 2),
+        (I, Rotation::R0, 6),
+        (O, Rotation::R0, 8),
+        (J, Rotation::R90, 1),
+        (S, Rotation::R0, 5),
+        (T, Rotation::R180, 3),
+        (L, Rotation::R270, 7),
+        (Z, Rotation::R90, 4),
+    ];
+    let size = UVec2::new(WIDTH as u32, 24);
+    let mut playfield = Playfield::new(size);
+    let mut mirrored = Playfield::new(size);
+    mirrored.set_mirrored(true);
+
+    let mut cleared = 0;
+    for (piece_type, rotation, x) in placements {
+        let piece = Piece {
+            position: IVec2::new(x, 20),
+            rotation,
+            ..Piece::new(piece_type)
+        };
+        let (t_spin, chain) = drop_piece(&mut playfield, piece);
+        let reflected = piece.mirrored(WIDTH as i32);
+        assert_eq!(
+            drop_piece(&mut mirrored, reflected),
+            (t_spin, chain.clone())
+        );
+        assert_eq!(stack(&mirrored), mirror_image(&playfield));
+        cleared += chain.iter().sum::<usize>();
+    }
+    assert_eq!(cleared, 1);
+}
+
+#[test]
+fn mirrored_pieces_spawn_reflected() {
+    let mut playfield = Playfield::new(UVec2::new(WIDTH as u32, 24));
+    playfield.set_mirrored(true);
+    let piece = Piece::new(PieceType::J).spawn(&playfield);
+    let mut cells: Vec<_> = iter_piece_cells(&piece).collect();
+    cells.sort_by_key(|cell| (cell.y, cell.x));
+    // the usual J covers 4..=6 with its corner at 4, this one looks like an L
+    assert_eq!(
+        cells,
+        [
+            IVec2::new(3, 22),
+            IVec2::new(4, 22),
+            IVec2::new(5, 22),
+            IVec2::new(5, 23)
+        ]
+    );
+}
+
+#[test]
+fn mirrored_t_spins_use_mirrored_corners() {
+    let mut playfield = playfield();
+    // a slot for a T that points left, full only with the nub side corners
+    for (x, y) in [(0, 0), (0, 2), (2, 0)] {
+        playfield.set_cell(IVec2::new(x, y), Cell::Garbage);
+    }
+    let piece = Piece {
+        position: IVec2::new(1, 1),
+        rotation: Rotation::R90,
+        rotated_last: true,
+        ..Piece::new(PieceType::T)
+    };
+    assert_eq!(playfield.t_spin(&piece), TSpin::Full);
+
+    playfield.set_mirrored(true);
+    let reflected = piece.mirrored(WIDTH as i32);
+    assert!(playfield.check_move(&reflected));
+    assert_eq!(playfield.t_spin(&reflected), TSpin::Full);
+}
@@ -0,0 +1,380 @@
+//! Hand-made puzzles loaded from `assets/puzzles`. Each one starts from a
+//! given board with a fixed queue and hold, and is solved by reaching its
+//! goal before the pieces run out.
+//!
+//! The folder is found the way the asset server finds `assets`, so the
+//! puzzles load from wherever the game is started.
+
+use std::{fmt, fs, path::Path};
+
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+use serde::Deserialize;
+
+use crate::setup::GameState;
+
+use super::{
+    hold::Hold,
+    piece_order::PieceOrder,
+    piece_types::{piece_from_letter, piece_letter, PieceType},
+    playfield::{Cell, Playfield, PlayfieldSize, TSpin},
+    GameSummary, PieceLocked, Score,
+};
+
+#[cfg(test)]
+mod tests;
+
+const PUZZLES_PATH: &str = "assets/puzzles";
+
+/// Rows at the top of the playfield that stay free for the spawning pieces.
+const SPAWN_ROWS: usize = 4;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PuzzleGoal {
+    /// Clear at least this many lines in total.
+    Lines(u32),
+    TSpinDouble,
+    PerfectClear,
+}
+
+impl PuzzleGoal {
+    pub fn label(self) -> String {
+        match self {
+            PuzzleGoal::Lines(1) => "Clear a line".to_string(),
+            PuzzleGoal::Lines(lines) => format!("Clear {lines} lines"),
+            PuzzleGoal::TSpinDouble => "Perform a T-spin double".to_string(),
+            PuzzleGoal::PerfectClear => "Clear the whole board".to_string(),
+        }
+    }
+
+    fn is_reached(self, locked: &PieceLocked, score: &Score) -> bool {
+        match self {
+            PuzzleGoal::Lines(lines) => score.lines >= lines,
+            PuzzleGoal::TSpinDouble => locked.t_spin == TSpin::Full && locked.cleared_rows == 2,
+            PuzzleGoal::PerfectClear => locked.perfect_clear,
+        }
+    }
+}
+
+/// A puzzle as it is written in its file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PuzzleFile {
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// Rows from the top down, the last one is at the bottom of the
+    /// playfield. `.` is empty, `G` garbage and a piece letter a block of
+    /// that piece.
+    board: Vec<String>,
+    /// Piece letters in the order they are dealt.
+    queue: String,
+    #[serde(default)]
+    hold: Option<String>,
+    goal: PuzzleGoal,
+}
+
+/// What is wrong with a puzzle file.
+#[derive(Debug)]
+pub enum PuzzleError {
+    Read(std::io::Error),
+    Parse(serde_json::Error),
+    TooManyRows {
+        rows: usize,
+        max: usize,
+    },
+    RowWidth {
+        row: usize,
+        width: usize,
+        expected: usize,
+    },
+    UnknownCell {
+        row: usize,
+        column: usize,
+        found: char,
+    },
+    FullRow {
+        row: usize,
+    },
+    UnknownPiece {
+        found: String,
+    },
+    EmptyQueue,
+    NoLines,
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PuzzleError::Read(e) => write!(f, "cannot read the file: {e}"),
+            PuzzleError::Parse(e) => write!(f, "not a valid puzzle: {e}"),
+            PuzzleError::TooManyRows { rows, max } => {
+                write!(
+                    f,
+                    "{rows} rows do not fit below the spawn, at most {max} do"
+                )
+            }
+            PuzzleError::RowWidth {
+                row,
+                width,
+                expected,
+            } => write!(f, "row {row} is {width} cells wide instead of {expected}"),
+            PuzzleError::UnknownCell { row, column, found } => write!(
+                f,
+                "row {row} has '{found}' in column {column}, use '.', 'G' or a piece letter"
+            ),
+            PuzzleError::FullRow { row } => write!(f, "row {row} is full and would clear at once"),
+            PuzzleError::UnknownPiece { found } => {
+                write!(f, "'{found}' is not a piece, use one of OJLSTZI")
+            }
+            PuzzleError::EmptyQueue => write!(f, "the queue has no pieces"),
+            PuzzleError::NoLines => write!(f, "a goal of 0 lines is solved already"),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    pub name: String,
+    pub description: String,
+    /// Cells from the bottom row up.
+    cells: Vec<Vec<Cell>>,
+    pub queue: Vec<PieceType>,
+    pub hold: Option<PieceType>,
+    pub goal: PuzzleGoal,
+}
+
+impl Puzzle {
+    /// Checks a puzzle file against a playfield of `size`.
+    fn parse(json: &str, size: UVec2) -> Result<Self, PuzzleError> {
+        let file: PuzzleFile = serde_json::from_str(json).map_err(PuzzleError::Parse)?;
+
+        let max = size.y as usize - SPAWN_ROWS;
+        if file.board.len() > max {
+            return Err(PuzzleError::TooManyRows {
+                rows: file.board.len(),
+                max,
+            });
+        }
+        let mut cells = file
+            .board
+            .iter()
+            .enumerate()
+            .map(|(index, line)| parse_row(index + 1, line, size.x as usize))
+            .collect::<Result<Vec<_>, _>>()?;
+        cells.reverse();
+
+        let queue = file
+            .queue
+            .chars()
+            .map(|letter| {
+                piece_from_letter(letter).ok_or_else(|| PuzzleError::UnknownPiece {
+                    found: letter.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if queue.is_empty() {
+            return Err(PuzzleError::EmptyQueue);
+        }
+
+        let hold = match file.hold {
+            None => None,
+            Some(letter) => {
+                let mut chars = letter.chars();
+                match (chars.next().and_then(piece_from_letter), chars.next()) {
+                    (Some(piece), None) => Some(piece),
+                    _ => return Err(PuzzleError::UnknownPiece { found: letter }),
+                }
+            }
+        };
+
+        if file.goal == PuzzleGoal::Lines(0) {
+            return Err(PuzzleError::NoLines);
+        }
+
+        Ok(Self {
+            name: file.name,
+            description: file.description,
+            cells,
+            queue,
+            hold,
+            goal: file.goal,
+        })
+    }
+
+    pub fn playfield(&self, size: UVec2) -> Playfield {
+        let mut playfield = Playfield::new(size);
+        for (y, row) in self.cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                playfield.set_cell(IVec2::new(x as i32, y as i32), *cell);
+            }
+        }
+        playfield
+    }
+
+    pub fn piece_order(&self) -> PieceOrder {
+        PieceOrder::fixed(self.queue.clone())
+    }
+}
+
+/// Reads one row of the board, `row` counts from the top starting at 1.
+fn parse_row(row: usize, line: &str, width: usize) -> Result<Vec<Cell>, PuzzleError> {
+    let cells = line
+        .chars()
+        .enumerate()
+        .map(|(column, found)| match found {
+            '.' => Ok(Cell::Empty),
+            'G' => Ok(Cell::Garbage),
+            letter => piece_from_letter(letter)
+                .map(Cell::Filled)
+                .ok_or(PuzzleError::UnknownCell {
+                    row,
+                    column: column + 1,
+                    found,
+                }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if cells.len() != width {
+        return Err(PuzzleError::RowWidth {
+            row,
+            width: cells.len(),
+            expected: width,
+        });
+    }
+    if cells.iter().all(|cell| !matches!(cell, Cell::Empty)) {
+        return Err(PuzzleError::FullRow { row });
+    }
+    Ok(cells)
+}
+
+/// The puzzles that could be loaded, and why the others could not.
+#[derive(Resource, Debug, Default)]
+pub struct Puzzles {
+    pub puzzles: Vec<Puzzle>,
+    pub errors: Vec<String>,
+}
+
+impl Puzzles {
+    /// Loads every `.json` file in the puzzle folder, sorted by file name.
+    fn load(folder: &Path, size: UVec2) -> Self {
+        let mut puzzles = Self::default();
+        let mut paths = match fs::read_dir(folder) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                warn!("No puzzles in {}: {e}", folder.display());
+                return puzzles;
+            }
+        };
+        paths.sort();
+
+        for path in paths {
+            let puzzle = fs::read_to_string(&path)
+                .map_err(PuzzleError::Read)
+                .and_then(|json| Puzzle::parse(&json, size));
+            match puzzle {
+                Ok(puzzle) => puzzles.puzzles.push(puzzle),
+                Err(e) => {
+                    let error = format!("{}: {e}", path.display());
+                    warn!("{error}");
+                    puzzles.errors.push(error);
+                }
+            }
+        }
+        puzzles
+    }
+}
+
+/// The puzzle that is played next, chosen in the menu.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SelectedPuzzle(pub usize);
+
+pub(super) fn load_puzzles(mut commands: Commands, playfield_size: Res<PlayfieldSize>) {
+    let PlayfieldSize(size) = *playfield_size;
+    let folder = FileAssetReader::get_base_path().join(PUZZLES_PATH);
+    commands.insert_resource(Puzzles::load(&folder, size));
+}
+
+/// A board playing a puzzle.
+#[derive(Component, Debug)]
+pub struct PuzzleBoard {
+    puzzle: Puzzle,
+    solved: bool,
+}
+
+impl PuzzleBoard {
+    pub fn new(puzzle: Puzzle) -> Self {
+        Self {
+            puzzle,
+            solved: false,
+        }
+    }
+}
+
+/// Checks the goal after every lock. Running out of pieces tops the board
+/// out, which fails the puzzle.
+pub(super) fn track_puzzle(
+    mut commands: Commands,
+    mut locked_events: EventReader<PieceLocked>,
+    mut puzzle_query: Query<(&mut PuzzleBoard, &Score)>,
+) {
+    for locked in locked_events.read() {
+        let Ok((mut board, score)) = puzzle_query.get_mut(locked.board) else {
+            continue;
+        };
+        if board.puzzle.goal.is_reached(locked, score) {
+            board.solved = true;
+            commands.insert_resource(NextState(Some(GameState::Finished)));
+        }
+    }
+}
+
+pub(super) fn puzzle_hud(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    puzzle_query: Query<(&PuzzleBoard, &PieceOrder, &Hold)>,
+) {
+    for (board, piece_order, hold) in puzzle_query.iter() {
+        egui::Window::new("Puzzle").show(contexts.ctx_mut(), |ui| {
+            ui.heading(&board.puzzle.name);
+            if !board.puzzle.description.is_empty() {
+                ui.label(&board.puzzle.description);
+            }
+            ui.label(format!("Goal: {}", board.puzzle.goal.label()));
+            ui.label(format!("Hold: {}", hold.piece.map_or('-', piece_letter)));
+            let queue: String = piece_order
+                .upcoming()
+                .iter()
+                .map(|p| piece_letter(*p))
+                .collect();
+            ui.label(format!("Queue: {queue}"));
+            if ui.button("Retry").clicked() {
+                commands.insert_resource(NextState(Some(GameState::SetupGame)));
+            }
+        });
+    }
+}
+
+/// Sums up the game before the board is torn down.
+pub(super) fn summarize_puzzle(
+    mut commands: Commands,
+    puzzle_query: Query<(&PuzzleBoard, &Score)>,
+) {
+    for (board, score) in puzzle_query.iter() {
+        let heading = if board.solved { "Solved" } else { "Not solved" };
+        commands.insert_resource(GameSummary {
+            heading: format!("{heading}: {}", board.puzzle.name),
+            rows: vec![
+                ("Goal", board.puzzle.goal.label()),
+                ("Pieces used", score.pieces.to_string()),
+                ("Lines", score.lines.to_string()),
+            ],
+        });
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+
+use super::{Puzzle, PuzzleError, PuzzleGoal, Puzzles, PUZZLES_PATH};
+
+const SIZE: UVec2 = UVec2::new(10, 24);
+
+/// A puzzle file with one board row and these fields replaced.
+fn puzzle(board: &str, queue: &str, extra: &str) -> String {
+    format!(
+        r#"{{ "name": "Test", "board": [{board}], "queue": "{queue}", "goal": {{ "lines": 1 }}{extra} }}"#
+    )
+}
+
+fn parse(json: &str) -> Result<Puzzle, PuzzleError> {
+    Puzzle::parse(json, SIZE)
+}
+
+#[test]
+fn valid_puzzle_parses() {
+    let puzzle = parse(&puzzle(r#""GGGGGGGGG.""#, "IO", r#", "hold": "T""#)).unwrap();
+    assert_eq!(puzzle.queue.len(), 2);
+    assert!(puzzle.hold.is_some());
+    assert_eq!(puzzle.goal, PuzzleGoal::Lines(1));
+    assert_eq!(puzzle.cells.len(), 1);
+}
+
+#[test]
+fn bundled_puzzles_parse() {
+    let folder = FileAssetReader::get_base_path().join(PUZZLES_PATH);
+    let puzzles = Puzzles::load(&folder, SIZE);
+    assert!(puzzles.errors.is_empty(), "{:?}", puzzles.errors);
+    assert_eq!(
+        puzzles.puzzles.len(),
+        fs::read_dir(&folder).unwrap().count()
+    );
+}
+
+#[test]
+fn unreadable_file_is_reported() {
+    let folder = std::env::temp_dir().join(format!("bevy-tetris-puzzles-{}", std::process::id()));
+    // a folder with a puzzle name can not be read as a file
+    fs::create_dir_all(folder.join("broken.json")).unwrap();
+    let puzzles = Puzzles::load(&folder, SIZE);
+    fs::remove_dir_all(&folder).unwrap();
+
+    assert!(puzzles.puzzles.is_empty());
+    assert_eq!(puzzles.errors.len(), 1);
+    assert!(puzzles.errors[0].contains("cannot read the file"));
+}
+
+#[test]
+fn malformed_json_is_a_parse_error() {
+    assert!(matches!(parse("{"), Err(PuzzleError::Parse(_))));
+    let unknown_field = puzzle(r#"".........G""#, "I", r#", "speed": 3"#);
+    assert!(matches!(parse(&unknown_field), Err(PuzzleError::Parse(_))));
+}
+
+#[test]
+fn board_must_fit_below_the_spawn() {
+    let rows = vec![r#""G.........""#; 21].join(", ");
+    assert!(matches!(
+        parse(&puzzle(&rows, "I", "")),
+        Err(PuzzleError::TooManyRows { rows: 21, max: 20 })
+    ));
+}
+
+#[test]
+fn rows_must_have_the_playfield_width() {
+    let board = r#""G........", "GG........""#;
+    assert!(matches!(
+        parse(&puzzle(board, "I", "")),
+        Err(PuzzleError::RowWidth {
+            row: 1,
+            width: 9,
+            expected: 10
+        })
+    ));
+}
+
+#[test]
+fn unknown_cells_are_located() {
+    assert!(matches!(
+        parse(&puzzle(r#""GG.x......""#, "I", "")),
+        Err(PuzzleError::UnknownCell {
+            row: 1,
+            column: 4,
+            found: 'x'
+        })
+    ));
+}
+
+#[test]
+fn full_rows_are_rejected() {
+    let board = r#""G.........", "GGGGGGGGGG""#;
+    assert!(matches!(
+        parse(&puzzle(board, "I", "")),
+        Err(PuzzleError::FullRow { row: 2 })
+    ));
+}
+
+#[test]
+fn pieces_must_be_known() {
+    let board = r#""G.........""#;
+    assert!(matches!(
+        parse(&puzzle(board, "IX", "")),
+        Err(PuzzleError::UnknownPiece { found }) if found == "X"
+    ));
+    assert!(matches!(
+        parse(&puzzle(board, "I", r#", "hold": "TT""#)),
+        Err(PuzzleError::UnknownPiece { found }) if found == "TT"
+    ));
+}
+
+#[test]
+fn queue_must_not_be_empty() {
+    assert!(matches!(
+        parse(&puzzle(r#""G.........""#, "", "")),
+        Err(PuzzleError::EmptyQueue)
+    ));
+}
+
+#[test]
+fn goal_must_need_lines() {
+    let json = puzzle(r#""G.........""#, "I", "").replace(r#""lines": 1"#, r#""lines": 0"#);
+    assert!(matches!(parse(&json), Err(PuzzleError::NoLines)));
+}
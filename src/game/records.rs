@@ -0,0 +1,40 @@
+use std::{collections::BTreeMap, fs, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Personal bests are kept next to the game.
+const RECORDS_PATH: &str = "records.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SprintRecord {
+    pub time: Duration,
+    /// Time at every 10 cleared lines.
+    pub splits: Vec<Duration>,
+}
+
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct Records {
+    /// Best sprint per number of lines.
+    #[serde(default)]
+    pub sprint: BTreeMap<u32, SprintRecord>,
+}
+
+impl Records {
+    pub fn load() -> Self {
+        match fs::read_to_string(RECORDS_PATH) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Ignoring broken {RECORDS_PATH}: {e}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let json = serde_json::to_string_pretty(self).expect("records serialize");
+        if let Err(e) = fs::write(RECORDS_PATH, json) {
+            warn!("Could not save {RECORDS_PATH}: {e}");
+        }
+    }
+}
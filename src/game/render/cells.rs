@@ -2,7 +2,9 @@ use bevy::prelude::*;
 
 use crate::{
     game::{
-        piece_types::{get_sprite_for_piece, EMPTY_SPRITE},
+        board::Board,
+        invisible::Invisible,
+        piece_types::{get_sprite_for_piece, EMPTY_SPRITE, GARBAGE_SPRITE},
         playfield::{Cell, Playfield, PlayfieldSize},
     },
     setup::CellTextures,
@@ -20,40 +22,86 @@ pub(super) fn spawn_cells(
     mut commands: Commands,
     cell_textures: Res<CellTextures>,
     playfield_size: Res<PlayfieldSize>,
+    board_query: Query<Entity, Added<Board>>,
 ) {
     let PlayfieldSize(size) = *playfield_size;
-    let texture_atlas = cell_textures.atlas.clone();
-    commands
-        .spawn((CellRenderGrid, SpatialBundle::default()))
-        .with_children(|cb| {
-            for y in 0..size.y {
-                for x in 0..size.x {
-                    cb.spawn((
-                        CellRender(UVec2::new(x, y)),
-                        SpriteSheetBundle {
-                            texture_atlas: texture_atlas.clone(),
-                            ..default()
-                        },
-                    ));
-                }
-            }
-        });
+    for board in board_query.iter() {
+        let texture_atlas = cell_textures.atlas.clone();
+        commands
+            .entity(board)
+            .insert(SpatialBundle::default())
+            .with_children(|cb| {
+                cb.spawn((Name::new("Cells"), CellRenderGrid, SpatialBundle::default()))
+                    .with_children(|cb| {
+                        for y in 0..size.y {
+                            for x in 0..size.x {
+                                cb.spawn((
+                                    CellRender(UVec2::new(x, y)),
+                                    SpriteSheetBundle {
+                                        texture_atlas: texture_atlas.clone(),
+                                        ..default()
+                                    },
+                                ));
+                            }
+                        }
+                    });
+            });
+    }
 }
 
 pub(super) fn update_cells(
     playfield_dimensions: Res<PlayfieldRenderSize>,
-    mut playfield_query: Query<&mut Playfield>,
-    mut background_grid_query: Query<(&CellRender, &mut Transform, &mut TextureAtlasSprite)>,
+    playfield_query: Query<(&Playfield, Option<&Invisible>)>,
+    grid_query: Query<(&Parent, &Children), With<CellRenderGrid>>,
+    mut cell_query: Query<(&CellRender, &mut Transform, &mut TextureAtlasSprite)>,
 ) {
-    if let Ok(mut playfield) = playfield_query.get_single_mut() {
-        for (CellRender(pos), mut transform, mut atlas_sprite) in background_grid_query.iter_mut() {
-            if let Some(cell) = playfield.get_mut(pos.as_ivec2()) {
-                *atlas_sprite = match cell {
+    for (parent, children) in grid_query.iter() {
+        let Ok((playfield, invisible)) = playfield_query.get(parent.get()) else {
+            continue;
+        };
+        let mut cells = cell_query.iter_many_mut(children);
+        while let Some((CellRender(pos), mut transform, mut atlas_sprite)) = cells.fetch_next() {
+            if let Some(cell) = playfield.get(pos.as_ivec2()) {
+                let sprite = match cell {
                     Cell::Empty => EMPTY_SPRITE,
                     Cell::Filled(piece_type) => get_sprite_for_piece(*piece_type),
-                }
+                    Cell::Item(_, item) => item.sprite(),
+                    Cell::Garbage => GARBAGE_SPRITE,
+                };
+                let visibility = match (cell, invisible) {
+                    (Cell::Empty, _) | (_, None) => 1.0,
+                    (_, Some(invisible)) => invisible.visibility(
+                        playfield
+                            .time_since_filled(pos.as_ivec2())
+                            .unwrap_or_default(),
+                    ),
+                };
+                *atlas_sprite = fade_to_empty(sprite, visibility);
             }
-            *transform = playfield_dimensions.get_transform(pos.as_vec2(), 0.0);
+            // a mirrored board is flipped back for the view
+            let position = if playfield.is_mirrored() {
+                playfield_dimensions.mirror(pos.as_vec2())
+            } else {
+                pos.as_vec2()
+            };
+            *transform = playfield_dimensions.get_transform(position, 0.0);
         }
     }
 }
+
+/// Blends a filled cell into an empty one, hidden cells look empty.
+fn fade_to_empty(sprite: TextureAtlasSprite, visibility: f32) -> TextureAtlasSprite {
+    if visibility >= 1.0 {
+        return sprite;
+    }
+    if visibility <= 0.0 {
+        return EMPTY_SPRITE;
+    }
+    let [r, g, b, a] = sprite.color.as_rgba_f32();
+    let [er, eg, eb, ea] = EMPTY_SPRITE.color.as_rgba_f32();
+    let mix = |filled: f32, empty: f32| empty + (filled - empty) * visibility;
+    TextureAtlasSprite {
+        color: Color::rgba(mix(r, er), mix(g, eg), mix(b, eb), mix(a, ea)),
+        ..sprite
+    }
+}
@@ -2,7 +2,9 @@ use bevy::prelude::*;
 
 use crate::{
     game::{
-        piece_types::{get_sprite_for_piece, iter_cells},
+        piece_types::{get_sprite_for_piece, iter_shape},
+        playfield::Playfield,
+        rotation::Rotation,
         Piece,
     },
     setup::CellTextures,
@@ -10,56 +12,59 @@ use crate::{
 
 use super::playfield_render_size::PlayfieldRenderSize;
 
-#[derive(Component)]
-pub(super) struct PieceRender;
-
 pub(super) fn update_piece_sprite(
     mut commands: Commands,
-    new_piece_query: Query<&Piece, Added<Piece>>,
-    piece_query: Query<&Piece>,
-    mut render_piece_query: Query<(&PieceRender, &mut Transform, Entity)>,
+    new_piece_query: Query<(Entity, &Piece, &Parent), Added<Piece>>,
+    mut piece_query: Query<(&Piece, &Parent, &mut Transform)>,
+    playfield_query: Query<&Playfield>,
     playfield_dimensions: Res<PlayfieldRenderSize>,
     cell_textures: Res<CellTextures>,
 ) {
-    if let Ok(&Piece {
-        piece_type,
-        rotation,
-        position,
-    }) = new_piece_query.get_single()
-    {
-        // we got a new piece replace RenderPiece entity
+    // mirrored boards are shown flipped back, their pieces with them
+    let flipped = |parent: &Parent| {
+        playfield_query
+            .get(parent.get())
+            .is_ok_and(Playfield::is_mirrored)
+    };
 
-        if let Ok((_, _, entity)) = render_piece_query.get_single() {
-            commands.entity(entity).despawn_recursive()
-        }
+    for (entity, piece, parent) in new_piece_query.iter() {
+        // we got a new piece, attach the sprites to it
+        let flipped = flipped(parent);
 
-        let sprite = get_sprite_for_piece(piece_type);
+        let sprite = get_sprite_for_piece(piece.piece_type);
         commands
-            .spawn((
-                PieceRender,
-                SpatialBundle {
-                    transform: playfield_dimensions.get_transform(position.as_vec2(), 1.0),
-                    ..default()
-                },
-            ))
+            .entity(entity)
+            .insert(SpatialBundle {
+                transform: playfield_dimensions.get_piece_transform(piece, 1.0, flipped),
+                ..default()
+            })
             .with_children(|cb| {
-                iter_cells(piece_type, rotation).for_each(|pos| {
-                    let texture_atlas = cell_textures.atlas.clone();
-                    cb.spawn(SpriteSheetBundle {
-                        sprite: sprite.clone(),
-                        texture_atlas,
-                        transform: Transform::from_translation(32.0 * pos.as_vec2().extend(0.0)),
-                        ..Default::default()
-                    });
-                })
+                let scale = piece.scale as f32;
+                iter_shape(piece.piece_type, Rotation::R0, piece.mirrored != flipped)
+                    .enumerate()
+                    .for_each(|(i, pos)| {
+                        let texture_atlas = cell_textures.atlas.clone();
+                        // the item sits on the first mino, like on the playfield
+                        let sprite = match piece.item {
+                            Some(item) if i == 0 => item.sprite(),
+                            _ => sprite.clone(),
+                        };
+                        cb.spawn(SpriteSheetBundle {
+                            sprite,
+                            texture_atlas,
+                            transform: Transform::from_translation(
+                                32.0 * scale * pos.as_vec2().extend(0.0),
+                            )
+                            .with_scale(Vec3::splat(scale)),
+                            ..Default::default()
+                        });
+                    })
             });
-    } else {
-        // update position of render piece
+    }
 
-        if let Ok(piece) = piece_query.get_single() {
-            let (_, mut transform, _) = render_piece_query.single_mut();
+    // update position of the pieces that already have their sprites
 
-            *transform = playfield_dimensions.get_piece_transform(piece, 1.0);
-        }
+    for (piece, parent, mut transform) in piece_query.iter_mut() {
+        *transform = playfield_dimensions.get_piece_transform(piece, 1.0, flipped(parent));
     }
 }
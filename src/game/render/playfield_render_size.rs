@@ -1,15 +1,19 @@
 use bevy::prelude::*;
 
 use crate::{
-    game::{playfield::PlayfieldSize, Piece},
+    game::{board::Board, playfield::PlayfieldSize, Piece},
     setup::CellTextures,
 };
 
+/// Gap between two boards in cells.
+const BOARD_GAP: f32 = 2.0;
+
 #[derive(Debug, Resource, Default)]
 pub(super) struct PlayfieldRenderSize {
     pub(super) cell_size: f32,
     pub(super) grid_size: Vec2,
     pub(super) scale: Vec3,
+    pub(super) board_count: usize,
 }
 
 impl PlayfieldRenderSize {
@@ -19,11 +23,34 @@ impl PlayfieldRenderSize {
         Transform::from_translation(position).with_scale(self.scale)
     }
 
-    pub fn get_piece_transform(&self, piece: &Piece, depth: f32) -> Transform {
-        let position = self.cell_size * piece.position.as_vec2() - 0.5 * self.grid_size;
+    /// Boards are placed next to each other, centered in the window.
+    pub fn get_board_transform(&self, index: usize) -> Transform {
+        let board_spacing = self.grid_size.x + BOARD_GAP * self.cell_size;
+        let center = 0.5 * (self.board_count.max(1) - 1) as f32;
+        Transform::from_xyz((index as f32 - center) * board_spacing, 0.0, 0.0)
+    }
+
+    /// Where a cell is shown on a board that is flipped left to right.
+    pub fn mirror(&self, position: Vec2) -> Vec2 {
+        let columns = self.grid_size.x / self.cell_size;
+        Vec2::new(columns - 1.0 - position.x, position.y)
+    }
+
+    /// Scaled pieces rotate around the center of their first mino. A piece
+    /// that is drawn reflected turns the other way.
+    pub fn get_piece_transform(&self, piece: &Piece, depth: f32, flipped: bool) -> Transform {
+        let center = piece.position.as_vec2() + 0.5 * (piece.scale - 1) as f32;
+        let center = if flipped { self.mirror(center) } else { center };
+        let position = self.cell_size * center - 0.5 * self.grid_size;
         let position = position.extend(depth);
 
-        let rotation = Quat::from_axis_angle(Vec3::Z, piece.rotation.into());
+        let angle: f32 = piece.rotation.into();
+        let angle = if piece.mirrored != flipped {
+            -angle
+        } else {
+            angle
+        };
+        let rotation = Quat::from_axis_angle(Vec3::Z, angle);
         Transform::from_translation(position)
             .with_rotation(rotation)
             .with_scale(self.scale)
@@ -35,8 +62,14 @@ pub(super) fn set_playfield_dimensions(
     mut windows: Query<&Window>,
     mut playfield_dimensions: ResMut<PlayfieldRenderSize>,
     cell_textures: Res<CellTextures>,
+    board_query: Query<&Board>,
 ) {
     let PlayfieldSize(size) = *playfield_size;
+    let board_count = board_query.iter().len().max(1);
+    let boards_size = Vec2::new(
+        board_count as f32 * size.x as f32 + (board_count - 1) as f32 * BOARD_GAP,
+        size.y as f32,
+    );
     let window = &windows.get_single_mut();
     if let Ok(window) = window {
         let resolution = &window.resolution;
@@ -45,7 +78,7 @@ pub(super) fn set_playfield_dimensions(
 
         let padded_res = 0.75 * resolution;
 
-        let max_cell_size = padded_res / size.as_vec2();
+        let max_cell_size = padded_res / boards_size;
 
         let cell_size = max_cell_size.min_element();
 
@@ -57,6 +90,16 @@ pub(super) fn set_playfield_dimensions(
             cell_size,
             grid_size,
             scale: Vec2::splat(scale).extend(1.0),
+            board_count,
         };
     }
 }
+
+pub(super) fn place_boards(
+    playfield_dimensions: Res<PlayfieldRenderSize>,
+    mut board_query: Query<(&Board, &mut Transform)>,
+) {
+    for (board, mut transform) in board_query.iter_mut() {
+        *transform = playfield_dimensions.get_board_transform(board.index);
+    }
+}
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Clone, Copy, Reflect)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum Rotation {
     #[default]
     R0,
@@ -19,3 +20,35 @@ impl From<Rotation> for f32 {
         }
     }
 }
+
+impl Rotation {
+    /// Rotates an offset around the origin.
+    pub fn rotate(self, IVec2 { x, y }: IVec2) -> IVec2 {
+        match self {
+            Rotation::R0 => IVec2 { x, y },
+            Rotation::R90 => IVec2 { x: -y, y: x },
+            Rotation::R180 => IVec2 { x: -x, y: -y },
+            Rotation::R270 => IVec2 { x: y, y: -x },
+        }
+    }
+
+    /// The next rotation counter-clockwise.
+    pub fn ccw(self) -> Self {
+        match self {
+            Rotation::R0 => Rotation::R90,
+            Rotation::R90 => Rotation::R180,
+            Rotation::R180 => Rotation::R270,
+            Rotation::R270 => Rotation::R0,
+        }
+    }
+
+    /// The next rotation clockwise.
+    pub fn cw(self) -> Self {
+        match self {
+            Rotation::R0 => Rotation::R270,
+            Rotation::R90 => Rotation::R0,
+            Rotation::R180 => Rotation::R90,
+            Rotation::R270 => Rotation::R180,
+        }
+    }
+}
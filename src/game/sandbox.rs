@@ -0,0 +1,344 @@
+//! A board to study openers on. Every lock is remembered so placements can
+//! be undone and redone, the queue can be changed at any time and gravity and
+//! lock delay are up to the player.
+
+use std::time::Duration;
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+
+use super::{
+    board::{FixedGravity, LockDelay},
+    hold::Hold,
+    piece_order::PieceOrder,
+    piece_types::{piece_from_letter, piece_letter, PieceType},
+    playfield::Playfield,
+    GameSummary, Piece, PieceLocked, Score, StepTimer,
+};
+
+#[cfg(test)]
+mod tests;
+
+const LOCK_DELAY: Duration = Duration::from_millis(500);
+
+/// Fastest gravity the sandbox offers, in rows per second.
+const MAX_GRAVITY: f32 = 60.0;
+
+const PIECE_TYPES: [PieceType; 7] = [
+    PieceType::O,
+    PieceType::J,
+    PieceType::L,
+    PieceType::S,
+    PieceType::T,
+    PieceType::Z,
+    PieceType::I,
+];
+
+/// The state of a board between two pieces, taken before the first piece is
+/// dealt and after every lock. No piece is falling at those times: the next
+/// one is still at the front of `piece_order`, so restoring the queue deals
+/// the same piece again and the falling piece needs no place here.
+#[derive(Debug)]
+struct Snapshot {
+    playfield: Playfield,
+    piece_order: PieceOrder,
+    score: Score,
+    hold: Option<PieceType>,
+}
+
+/// Entries that can be stepped back and forth through. Recording an entry
+/// drops the ones that were stepped back from.
+#[derive(Debug)]
+struct History<T> {
+    entries: Vec<T>,
+    /// The entry that is current.
+    position: usize,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            entries: vec![],
+            position: 0,
+        }
+    }
+}
+
+impl<T> History<T> {
+    fn record(&mut self, entry: T) {
+        self.entries.truncate(self.position + 1);
+        self.entries.push(entry);
+        self.position = self.entries.len() - 1;
+    }
+
+    fn undo(&mut self) -> Option<&T> {
+        self.position = self.position.checked_sub(1)?;
+        self.entries.get(self.position)
+    }
+
+    fn redo(&mut self) -> Option<&T> {
+        if !self.can_redo() {
+            return None;
+        }
+        self.position += 1;
+        self.entries.get(self.position)
+    }
+
+    fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+
+    fn can_redo(&self) -> bool {
+        self.position + 1 < self.entries.len()
+    }
+
+    /// Entries after the first one.
+    fn steps(&self) -> usize {
+        self.entries.len().saturating_sub(1)
+    }
+}
+
+/// Something the player asked for in the sandbox window.
+#[derive(Debug)]
+enum SandboxAction {
+    Undo,
+    Redo,
+    /// Deal this piece next.
+    Next(PieceType),
+    /// Replace the queue with these pieces.
+    Queue(Vec<PieceType>),
+    /// Rows per second, 0 turns gravity off.
+    Gravity(f32),
+    LockDelay(bool),
+}
+
+/// A board in the sandbox.
+#[derive(Component, Debug)]
+pub struct Sandbox {
+    /// The board after every lock, the first entry is the start.
+    history: History<Snapshot>,
+    actions: Vec<SandboxAction>,
+    gravity: f32,
+    lock_delay: bool,
+    /// The queue as it is typed in the window.
+    queue_text: String,
+    queue_error: Option<String>,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            history: History::default(),
+            actions: vec![],
+            gravity: 0.0,
+            lock_delay: false,
+            queue_text: String::new(),
+            queue_error: None,
+        }
+    }
+}
+
+fn parse_queue(text: &str) -> Result<Vec<PieceType>, String> {
+    text.chars()
+        .filter(|letter| !letter.is_whitespace())
+        .map(|letter| {
+            piece_from_letter(letter.to_ascii_uppercase())
+                .ok_or_else(|| format!("'{letter}' is not a piece, use one of OJLSTZI"))
+        })
+        .collect()
+}
+
+/// Sets the gravity of a board, zero removes it so pieces only lock when
+/// they are dropped.
+fn set_gravity(board: &mut EntityCommands, gravity: f32) {
+    if gravity > 0.0 {
+        board.insert((
+            StepTimer(Timer::from_seconds(1.0 / gravity, TimerMode::Repeating)),
+            FixedGravity,
+        ));
+    } else {
+        board.remove::<(StepTimer, FixedGravity)>();
+    }
+}
+
+fn set_lock_delay(board: &mut EntityCommands, lock_delay: bool) {
+    if lock_delay {
+        board.insert(LockDelay(Timer::new(LOCK_DELAY, TimerMode::Once)));
+    } else {
+        board.remove::<LockDelay>();
+    }
+}
+
+fn snapshot(
+    playfield: &Playfield,
+    piece_order: &PieceOrder,
+    score: &Score,
+    hold: &Hold,
+) -> Snapshot {
+    Snapshot {
+        playfield: playfield.clone(),
+        piece_order: piece_order.clone(),
+        score: score.clone(),
+        hold: hold.piece,
+    }
+}
+
+/// Remembers the board at the start and after every lock and carries out what
+/// the player asked for. Going back in the history takes the falling piece
+/// away, the next one is dealt from the queue of that time.
+///
+/// Runs before pieces are dealt, so the first snapshot still holds the whole
+/// queue and a piece dealt in the same frame as an undo can not survive it.
+pub(super) fn run_sandbox(
+    mut commands: Commands,
+    mut locked_events: EventReader<PieceLocked>,
+    piece_query: Query<(Entity, &Parent), With<Piece>>,
+    mut sandbox_query: Query<(
+        Entity,
+        &mut Sandbox,
+        &mut Playfield,
+        &mut PieceOrder,
+        &mut Score,
+        &mut Hold,
+    )>,
+) {
+    let locked_boards: Vec<_> = locked_events.read().map(|locked| locked.board).collect();
+
+    for (board, mut sandbox, mut playfield, mut piece_order, mut score, mut hold) in
+        sandbox_query.iter_mut()
+    {
+        if sandbox.history.entries.is_empty() || locked_boards.contains(&board) {
+            // a new placement replaces the ones that were undone
+            let current = snapshot(&playfield, &piece_order, &score, &hold);
+            sandbox.history.record(current);
+        }
+
+        let sandbox = &mut *sandbox;
+        for action in std::mem::take(&mut sandbox.actions) {
+            let saved = match action {
+                SandboxAction::Undo => sandbox.history.undo(),
+                SandboxAction::Redo => sandbox.history.redo(),
+                SandboxAction::Next(piece_type) => {
+                    piece_order.push_next(piece_type);
+                    None
+                }
+                SandboxAction::Queue(pieces) => {
+                    piece_order.set_upcoming(pieces);
+                    None
+                }
+                SandboxAction::Gravity(gravity) => {
+                    set_gravity(&mut commands.entity(board), gravity);
+                    None
+                }
+                SandboxAction::LockDelay(lock_delay) => {
+                    set_lock_delay(&mut commands.entity(board), lock_delay);
+                    None
+                }
+            };
+            let Some(saved) = saved else {
+                continue;
+            };
+
+            *playfield = saved.playfield.clone();
+            *piece_order = saved.piece_order.clone();
+            *score = saved.score.clone();
+            *hold = Hold::new(saved.hold);
+            for (piece, parent) in piece_query.iter() {
+                if parent.get() == board {
+                    commands.entity(piece).despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn sandbox_hud(
+    mut contexts: EguiContexts,
+    mut sandbox_query: Query<(&mut Sandbox, &PieceOrder)>,
+) {
+    for (mut sandbox, piece_order) in sandbox_query.iter_mut() {
+        egui::Window::new("Sandbox").show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                let can_undo = sandbox.history.can_undo();
+                let can_redo = sandbox.history.can_redo();
+                if ui
+                    .add_enabled(can_undo, egui::Button::new("Undo"))
+                    .clicked()
+                {
+                    sandbox.actions.push(SandboxAction::Undo);
+                }
+                if ui
+                    .add_enabled(can_redo, egui::Button::new("Redo"))
+                    .clicked()
+                {
+                    sandbox.actions.push(SandboxAction::Redo);
+                }
+                ui.label(format!(
+                    "Placement {} / {}",
+                    sandbox.history.position,
+                    sandbox.history.steps()
+                ));
+            });
+
+            ui.separator();
+            let queue: String = piece_order
+                .upcoming()
+                .iter()
+                .map(|p| piece_letter(*p))
+                .collect();
+            ui.label(format!("Queue: {queue}"));
+            ui.horizontal(|ui| {
+                ui.label("Next:");
+                for piece_type in PIECE_TYPES {
+                    if ui.button(piece_letter(piece_type).to_string()).clicked() {
+                        sandbox.actions.push(SandboxAction::Next(piece_type));
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut sandbox.queue_text);
+                if ui.button("Set queue").clicked() {
+                    match parse_queue(&sandbox.queue_text) {
+                        Ok(pieces) => {
+                            sandbox.actions.push(SandboxAction::Queue(pieces));
+                            sandbox.queue_error = None;
+                        }
+                        Err(e) => sandbox.queue_error = Some(e),
+                    }
+                }
+            });
+            if let Some(error) = &sandbox.queue_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            ui.separator();
+            let mut gravity = sandbox.gravity;
+            let slider = egui::Slider::new(&mut gravity, 0.0..=MAX_GRAVITY)
+                .logarithmic(true)
+                .text("Gravity (rows/s)");
+            if ui.add(slider).changed() {
+                sandbox.gravity = gravity;
+                sandbox.actions.push(SandboxAction::Gravity(gravity));
+            }
+            let mut lock_delay = sandbox.lock_delay;
+            if ui.checkbox(&mut lock_delay, "Lock delay").changed() {
+                sandbox.lock_delay = lock_delay;
+                sandbox.actions.push(SandboxAction::LockDelay(lock_delay));
+            }
+        });
+    }
+}
+
+/// Sums up the session before the board is torn down.
+pub(super) fn summarize_sandbox(mut commands: Commands, sandbox_query: Query<(&Sandbox, &Score)>) {
+    for (sandbox, score) in sandbox_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: "Sandbox".to_string(),
+            rows: vec![
+                ("Placements", sandbox.history.position.to_string()),
+                ("Pieces", score.pieces.to_string()),
+                ("Lines", score.lines.to_string()),
+            ],
+        });
+    }
+}
@@ -0,0 +1,45 @@
+use super::History;
+
+fn history(entries: &[u32]) -> History<u32> {
+    let mut history = History::default();
+    for entry in entries {
+        history.record(*entry);
+    }
+    history
+}
+
+#[test]
+fn undo_steps_back_to_the_start() {
+    let mut history = history(&[0, 1, 2]);
+    assert_eq!(history.steps(), 2);
+    assert_eq!(history.undo(), Some(&1));
+    assert_eq!(history.undo(), Some(&0));
+    assert!(!history.can_undo());
+    assert_eq!(history.undo(), None);
+    assert_eq!(history.position, 0);
+}
+
+#[test]
+fn redo_returns_to_undone_entries() {
+    let mut history = history(&[0, 1, 2]);
+    assert!(!history.can_redo());
+    assert_eq!(history.redo(), None);
+    history.undo();
+    history.undo();
+    assert_eq!(history.redo(), Some(&1));
+    assert_eq!(history.redo(), Some(&2));
+    assert_eq!(history.redo(), None);
+    assert_eq!(history.position, 2);
+}
+
+#[test]
+fn recording_drops_the_undone_entries() {
+    let mut history = history(&[0, 1, 2, 3]);
+    history.undo();
+    history.undo();
+    history.record(4);
+    assert_eq!(history.entries, vec![0, 1, 4]);
+    assert_eq!(history.position, 2);
+    assert!(!history.can_redo());
+    assert_eq!(history.undo(), Some(&1));
+}
@@ -0,0 +1,51 @@
+use super::playfield::TSpin;
+
+#[cfg(test)]
+mod tests;
+
+/// Points for a lock following the guideline: the kind of clear, back to back
+/// bonus, combo and perfect clear, all times the level. `chain` holds the rows
+/// of every step of the clear, the steps after the first are cascades that
+/// score their rows once more than the step before.
+pub(super) fn lock_points(
+    chain: &[usize],
+    t_spin: TSpin,
+    back_to_back: bool,
+    combo: u32,
+    perfect_clear: bool,
+    level: u32,
+) -> u32 {
+    let rows = chain.first().copied().unwrap_or(0);
+    let mut points = match (t_spin, rows) {
+        (TSpin::None, 0) => 0,
+        (TSpin::None, 1) => 100,
+        (TSpin::None, 2) => 300,
+        (TSpin::None, 3) => 500,
+        (TSpin::None, _) => 800,
+        (TSpin::Mini, 0) => 100,
+        (TSpin::Mini, 1) => 200,
+        (TSpin::Mini, _) => 400,
+        (TSpin::Full, 0) => 400,
+        (TSpin::Full, 1) => 800,
+        (TSpin::Full, 2) => 1200,
+        (TSpin::Full, _) => 1600,
+    };
+    if back_to_back {
+        points = points * 3 / 2;
+    }
+    points += 50 * combo.saturating_sub(1);
+    if perfect_clear {
+        points += match rows {
+            1 => 800,
+            2 => 1200,
+            3 => 1800,
+            _ if back_to_back => 3200,
+            _ => 2000,
+        };
+    }
+    points += (2..)
+        .zip(chain.iter().skip(1))
+        .map(|(step, rows)| step * *rows as u32 * 100)
+        .sum::<u32>();
+    points * level
+}
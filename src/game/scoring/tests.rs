@@ -0,0 +1,35 @@
+use super::{lock_points, TSpin};
+
+#[test]
+fn clears_score_by_kind() {
+    let points = |rows, t_spin| lock_points(&[rows], t_spin, false, 1, false, 1);
+    assert_eq!(points(0, TSpin::None), 0);
+    assert_eq!(points(1, TSpin::None), 100);
+    assert_eq!(points(2, TSpin::None), 300);
+    assert_eq!(points(3, TSpin::None), 500);
+    assert_eq!(points(4, TSpin::None), 800);
+    assert_eq!(points(1, TSpin::Mini), 200);
+    assert_eq!(points(2, TSpin::Full), 1200);
+    assert_eq!(lock_points(&[], TSpin::Full, false, 0, false, 1), 400);
+}
+
+#[test]
+fn bonuses_add_up() {
+    // back to back tetris
+    assert_eq!(lock_points(&[4], TSpin::None, true, 1, false, 1), 1200);
+    // fourth clear in a row
+    assert_eq!(lock_points(&[1], TSpin::None, false, 4, false, 1), 250);
+    // perfect clear with a double
+    assert_eq!(lock_points(&[2], TSpin::None, false, 1, true, 1), 1500);
+    // everything scales with the level
+    assert_eq!(lock_points(&[4], TSpin::None, false, 1, false, 3), 2400);
+}
+
+#[test]
+fn cascades_score_more_every_step() {
+    // a single followed by cascades of two and one rows
+    assert_eq!(
+        lock_points(&[1, 2, 1], TSpin::None, false, 1, false, 1),
+        100 + 2 * 2 * 100 + 3 * 100
+    );
+}
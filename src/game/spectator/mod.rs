@@ -0,0 +1,261 @@
+//! A local WebSocket server that streams the running game to spectators,
+//! e.g. a browser overlay for streaming.
+//!
+//! Every message is a JSON text frame. A `snapshot` is sent whenever the
+//! playfield or the falling piece of a board changes, a `lock` for every
+//! piece that locks. New spectators first get the latest snapshot of every
+//! board.
+
+#[cfg(test)]
+mod tests;
+mod websocket;
+
+use std::{
+    collections::HashMap,
+    io,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use super::{
+    board::Board,
+    piece_order::PieceOrder,
+    piece_types::{iter_piece_cells, piece_letter},
+    playfield::{Cell, Playfield, TSpin},
+    rotation::Rotation,
+    Piece, PieceLocked, Score,
+};
+
+/// Starts the spectator server on `address`.
+pub struct SpectatorPlugin {
+    pub address: String,
+}
+
+impl Plugin for SpectatorPlugin {
+    fn name(&self) -> &str {
+        "spectator_server"
+    }
+
+    fn build(&self, app: &mut App) {
+        let server = match SpectatorServer::bind(&self.address) {
+            Ok(server) => server,
+            Err(e) => {
+                error!(
+                    "Could not start spectator server on {}, playing without it: {e}",
+                    self.address
+                );
+                return;
+            }
+        };
+        info!("Spectators can connect to ws://{}", server.local_addr);
+
+        app.insert_resource(server).add_systems(
+            PostUpdate,
+            (accept_spectators, send_snapshots, send_locks).chain(),
+        );
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SpectatorMessage {
+    Snapshot(Snapshot),
+    Lock(LockEvent),
+}
+
+#[derive(Serialize, Debug)]
+struct Snapshot {
+    board: usize,
+    name: String,
+    width: u32,
+    height: u32,
+    /// Rows from the bottom up, filled cells hold the letter of their piece
+    /// or `G` for garbage.
+    cells: Vec<Vec<Option<char>>>,
+    piece: Option<ActivePiece>,
+    queue: Vec<char>,
+    score: u32,
+    level: u32,
+}
+
+#[derive(Serialize, Debug)]
+struct ActivePiece {
+    #[serde(rename = "type")]
+    piece: char,
+    rotation: Rotation,
+    x: i32,
+    y: i32,
+    /// Playfield coordinates of the minos.
+    cells: Vec<[i32; 2]>,
+}
+
+#[derive(Serialize, Debug)]
+struct LockEvent {
+    board: usize,
+    cleared_rows: usize,
+    /// Clear steps of a cascade.
+    chain: u32,
+    t_spin: TSpin,
+    combo: u32,
+    back_to_back: bool,
+    perfect_clear: bool,
+    score: u32,
+}
+
+#[derive(Resource)]
+struct SpectatorServer {
+    listener: TcpListener,
+    local_addr: SocketAddr,
+    spectators: Vec<Sender<String>>,
+    /// The last snapshot sent for each board, for new spectators and to skip
+    /// snapshots that did not change.
+    snapshots: HashMap<Entity, String>,
+}
+
+impl SpectatorServer {
+    fn bind(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            local_addr: listener.local_addr()?,
+            listener,
+            spectators: vec![],
+            snapshots: HashMap::new(),
+        })
+    }
+
+    fn broadcast(&mut self, message: &str) {
+        self.spectators
+            .retain(|spectator| spectator.send(message.to_string()).is_ok());
+    }
+}
+
+/// Every spectator gets a thread that does the handshake and then writes
+/// the messages it receives, so a slow spectator does not hold up the game.
+fn serve_spectator(mut stream: TcpStream, messages: Receiver<String>) {
+    let result = stream
+        .set_nonblocking(false)
+        .and_then(|_| stream.set_nodelay(true))
+        .and_then(|_| websocket::handshake(&mut stream))
+        .and_then(|_| {
+            messages
+                .iter()
+                .try_for_each(|message| websocket::write_text(&mut stream, &message))
+        });
+    if let Err(e) = result {
+        info!("Spectator left: {e}");
+    }
+}
+
+fn accept_spectators(mut server: ResMut<SpectatorServer>) {
+    while let Ok((stream, address)) = server.listener.accept() {
+        info!("Spectator connected from {address}");
+        let (sender, receiver) = channel();
+        for snapshot in server.snapshots.values() {
+            let _ = sender.send(snapshot.clone());
+        }
+        server.spectators.push(sender);
+        thread::spawn(move || serve_spectator(stream, receiver));
+    }
+}
+
+fn send_snapshots(
+    mut server: ResMut<SpectatorServer>,
+    mut removed_boards: RemovedComponents<Board>,
+    changed_pieces: Query<&Parent, Changed<Piece>>,
+    piece_query: Query<(&Piece, &Parent)>,
+    board_query: Query<(
+        Entity,
+        Ref<Playfield>,
+        &Board,
+        &Name,
+        &Score,
+        Option<&PieceOrder>,
+    )>,
+) {
+    for board in removed_boards.read() {
+        server.snapshots.remove(&board);
+    }
+
+    for (entity, playfield, board, name, score, piece_order) in board_query.iter() {
+        let piece_changed = changed_pieces.iter().any(|parent| parent.get() == entity);
+        if !playfield.is_changed() && !piece_changed {
+            continue;
+        }
+
+        let piece = piece_query
+            .iter()
+            .find(|(_, parent)| parent.get() == entity)
+            .map(|(piece, _)| ActivePiece {
+                piece: piece_letter(piece.piece_type),
+                rotation: piece.rotation,
+                x: piece.position.x,
+                y: piece.position.y,
+                cells: iter_piece_cells(piece).map(|c| [c.x, c.y]).collect(),
+            });
+        let snapshot = Snapshot {
+            board: board.index,
+            name: name.to_string(),
+            width: playfield.size().x,
+            height: playfield.size().y,
+            cells: cells(&playfield),
+            piece,
+            queue: piece_order.map_or(vec![], |order| {
+                order.upcoming().iter().copied().map(piece_letter).collect()
+            }),
+            score: score.score,
+            level: score.level(),
+        };
+
+        let message = serde_json::to_string(&SpectatorMessage::Snapshot(snapshot))
+            .expect("snapshots serialize");
+        if server.snapshots.get(&entity) != Some(&message) {
+            server.broadcast(&message);
+            server.snapshots.insert(entity, message);
+        }
+    }
+}
+
+fn cells(playfield: &Playfield) -> Vec<Vec<Option<char>>> {
+    let size = playfield.size().as_ivec2();
+    (0..size.y)
+        .map(|y| {
+            (0..size.x)
+                .map(|x| match playfield.get(IVec2::new(x, y)) {
+                    Some(Cell::Filled(piece_type) | Cell::Item(piece_type, _)) => {
+                        Some(piece_letter(*piece_type))
+                    }
+                    Some(Cell::Garbage) => Some('G'),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn send_locks(
+    mut server: ResMut<SpectatorServer>,
+    mut locked_events: EventReader<PieceLocked>,
+    board_query: Query<(&Board, &Score)>,
+) {
+    for locked in locked_events.read() {
+        let Ok((board, score)) = board_query.get(locked.board) else {
+            continue;
+        };
+        let message = SpectatorMessage::Lock(LockEvent {
+            board: board.index,
+            cleared_rows: locked.cleared_rows,
+            chain: locked.chain,
+            t_spin: locked.t_spin,
+            combo: locked.combo,
+            back_to_back: locked.back_to_back,
+            perfect_clear: locked.perfect_clear,
+            score: score.score,
+        });
+        server.broadcast(&serde_json::to_string(&message).expect("locks serialize"));
+    }
+}
@@ -0,0 +1,195 @@
+//! A stand-in spectator that connects to a headless game and checks the
+//! messages it gets.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::channel,
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use serde_json::Value;
+
+use crate::{game::GameLogicPlugin, setup::GameState};
+
+use super::{SpectatorPlugin, SpectatorServer};
+
+/// Example key and answer from RFC 6455.
+const KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+const ACCEPT: &str = "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=";
+
+fn headless_game() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        GameLogicPlugin,
+        SpectatorPlugin {
+            address: "127.0.0.1:0".to_string(),
+        },
+    ))
+    .add_state::<GameState>()
+    .init_resource::<Input<KeyCode>>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        50,
+    )))
+    .insert_resource(NextState(Some(GameState::SetupGame)));
+    app
+}
+
+/// Reads one unmasked frame and returns its text.
+fn read_text_frame(stream: &mut impl Read) -> std::io::Result<String> {
+    let mut header = [0; 2];
+    stream.read_exact(&mut header)?;
+    assert_eq!(header[0], 0x81, "expected a final text frame");
+    assert_eq!(header[1] & 0x80, 0, "servers do not mask frames");
+
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            stream.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as usize
+        }
+        127 => {
+            let mut len = [0; 8];
+            stream.read_exact(&mut len)?;
+            u64::from_be_bytes(len) as usize
+        }
+        len => len as usize,
+    };
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload)?;
+    Ok(String::from_utf8(payload).expect("text frames are UTF-8"))
+}
+
+fn assert_letter(value: &Value) {
+    let letter = value.as_str().expect("letters are strings");
+    assert!(letter.len() == 1 && "OJLSTZIG".contains(letter), "{letter}");
+}
+
+fn check_snapshot(message: &Value) {
+    assert!(message["board"].is_u64());
+    assert!(message["name"].is_string());
+    assert!(message["score"].is_u64());
+    assert!(message["level"].is_u64());
+
+    let width = message["width"].as_u64().unwrap() as usize;
+    let rows = message["cells"].as_array().unwrap();
+    assert_eq!(rows.len() as u64, message["height"].as_u64().unwrap());
+    for row in rows {
+        let row = row.as_array().unwrap();
+        assert_eq!(row.len(), width);
+        row.iter()
+            .filter(|cell| !cell.is_null())
+            .for_each(assert_letter);
+    }
+
+    message["queue"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .for_each(assert_letter);
+
+    let piece = &message["piece"];
+    if !piece.is_null() {
+        assert_letter(&piece["type"]);
+        assert!(piece["rotation"].is_string());
+        assert!(piece["x"].is_i64() && piece["y"].is_i64());
+        let cells = piece["cells"].as_array().unwrap();
+        assert_eq!(cells.len(), 4);
+        for cell in cells {
+            assert_eq!(cell.as_array().unwrap().len(), 2);
+        }
+    }
+}
+
+fn check_lock(message: &Value) {
+    assert!(message["board"].is_u64());
+    assert!(message["cleared_rows"].is_u64());
+    assert!(["none", "mini", "full"].contains(&message["t_spin"].as_str().unwrap()));
+    assert!(message["combo"].is_u64());
+    assert!(message["back_to_back"].is_boolean());
+    assert!(message["perfect_clear"].is_boolean());
+    assert!(message["score"].is_u64());
+}
+
+#[test]
+fn spectator_receives_snapshots_and_locks() {
+    let mut app = headless_game();
+    app.update();
+    let address = app.world.resource::<SpectatorServer>().local_addr;
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    write!(
+        stream,
+        "GET / HTTP/1.1\r\n\
+         Host: {address}\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: {KEY}\r\n\
+         Sec-WebSocket-Version: 13\r\n\r\n"
+    )
+    .unwrap();
+    app.update();
+
+    let mut reader = BufReader::new(stream);
+    let mut response = vec![];
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim_end().is_empty() {
+            break;
+        }
+        response.push(line.trim_end().to_string());
+    }
+    assert!(response[0].starts_with("HTTP/1.1 101"), "{response:?}");
+    assert!(response.contains(&format!("Sec-WebSocket-Accept: {ACCEPT}")));
+
+    let (sender, messages) = channel();
+    thread::spawn(move || {
+        while let Ok(text) = read_text_frame(&mut reader) {
+            if sender.send(text).is_err() {
+                break;
+            }
+        }
+    });
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let (mut snapshots, mut locks) = (0, 0);
+    while snapshots == 0 || locks == 0 {
+        assert!(Instant::now() < deadline, "no snapshot and lock arrived");
+        app.update();
+        while let Ok(text) = messages.recv_timeout(Duration::from_millis(1)) {
+            let message: Value = serde_json::from_str(&text).unwrap();
+            match message["type"].as_str() {
+                Some("snapshot") => {
+                    check_snapshot(&message);
+                    snapshots += 1;
+                }
+                Some("lock") => {
+                    check_lock(&message);
+                    locks += 1;
+                }
+                other => panic!("unknown message type {other:?}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn busy_port_plays_without_spectators() {
+    let taken = TcpListener::bind("127.0.0.1:0").expect("a free port");
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        SpectatorPlugin {
+            address: taken.local_addr().expect("bound").to_string(),
+        },
+    ));
+    app.update();
+    assert!(app.world.get_resource::<SpectatorServer>().is_none());
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::setup::GameState;
+
+use super::{
+    clock::{format_time, GameClock},
+    records::{Records, SprintRecord},
+    GameSummary, Score,
+};
+
+/// Number of lines a sprint asks for, chosen in the menu.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SprintGoal(pub u32);
+
+impl SprintGoal {
+    pub const ALL: [SprintGoal; 3] = [SprintGoal(20), SprintGoal(40), SprintGoal(100)];
+}
+
+impl Default for SprintGoal {
+    fn default() -> Self {
+        SprintGoal(40)
+    }
+}
+
+/// Lines between two splits.
+const SPLIT_LINES: u32 = 10;
+
+/// A board racing to clear a number of lines.
+#[derive(Component, Debug)]
+pub struct Sprint {
+    goal: u32,
+    /// Time at every [`SPLIT_LINES`] cleared lines.
+    splits: Vec<Duration>,
+}
+
+impl Sprint {
+    pub fn new(SprintGoal(goal): SprintGoal) -> Self {
+        Self {
+            goal,
+            splits: vec![],
+        }
+    }
+}
+
+fn pieces_per_second(score: &Score, elapsed: Duration) -> f32 {
+    if elapsed.is_zero() {
+        0.0
+    } else {
+        score.pieces as f32 / elapsed.as_secs_f32()
+    }
+}
+
+/// Signed difference to a split of the personal best, e.g. `-1.234`.
+fn format_difference(time: Duration, best: Duration) -> String {
+    if time >= best {
+        format!("+{:.3}", (time - best).as_secs_f32())
+    } else {
+        format!("-{:.3}", (best - time).as_secs_f32())
+    }
+}
+
+/// Records splits and finishes the game once the goal is cleared.
+pub(super) fn track_sprint(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut records: ResMut<Records>,
+    mut sprint_query: Query<(&Score, &mut Sprint)>,
+) {
+    for (score, mut sprint) in sprint_query.iter_mut() {
+        let lines = score.lines.min(sprint.goal);
+        while sprint.splits.len() < (lines / SPLIT_LINES) as usize {
+            sprint.splits.push(clock.elapsed);
+        }
+        if score.lines < sprint.goal {
+            continue;
+        }
+
+        let time = clock.elapsed;
+        let personal_best = records.sprint.get(&sprint.goal).map(|record| record.time);
+        let new_record = personal_best.is_none_or(|best| time < best);
+        if new_record {
+            records.sprint.insert(
+                sprint.goal,
+                SprintRecord {
+                    time,
+                    splits: sprint.splits.clone(),
+                },
+            );
+            records.save();
+        }
+
+        let mut rows = vec![
+            ("Time", format_time(time)),
+            (
+                "Pieces per second",
+                format!("{:.2}", pieces_per_second(score, time)),
+            ),
+        ];
+        if let Some(best) = personal_best {
+            rows.push(("Personal best", format_time(best)));
+            rows.push(("Difference", format_difference(time, best)));
+        }
+        commands.insert_resource(GameSummary {
+            heading: if new_record {
+                format!("{} lines - new personal best!", sprint.goal)
+            } else {
+                format!("{} lines", sprint.goal)
+            },
+            rows,
+        });
+        commands.insert_resource(NextState(Some(GameState::Finished)));
+    }
+}
+
+pub(super) fn sprint_hud(
+    mut contexts: EguiContexts,
+    clock: Res<GameClock>,
+    records: Res<Records>,
+    sprint_query: Query<(&Score, &Sprint)>,
+) {
+    for (score, sprint) in sprint_query.iter() {
+        let best = records.sprint.get(&sprint.goal);
+        egui::Window::new("Sprint").show(contexts.ctx_mut(), |ui| {
+            ui.heading(format_time(clock.elapsed));
+            ui.label(format!(
+                "Lines: {} / {}",
+                score.lines.min(sprint.goal),
+                sprint.goal
+            ));
+            ui.label(format!(
+                "Pieces per second: {:.2}",
+                pieces_per_second(score, clock.elapsed)
+            ));
+
+            let Some(best) = best else {
+                return;
+            };
+            ui.label(format!("Personal best: {}", format_time(best.time)));
+            for (i, (split, best_split)) in sprint.splits.iter().zip(&best.splits).enumerate() {
+                ui.label(format!(
+                    "{} lines: {} ({})",
+                    (i as u32 + 1) * SPLIT_LINES,
+                    format_time(*split),
+                    format_difference(*split, *best_split)
+                ));
+            }
+        });
+    }
+}
@@ -0,0 +1,235 @@
+//! Survival: garbage rises from the bottom on a timer that speeds up the
+//! longer the game goes, and the game lasts until the stack tops out.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::*;
+use rand_core::RngCore;
+
+use super::{
+    board::ToppedOut,
+    clock::{format_time, GameClock},
+    playfield::{GarbagePattern, Playfield},
+    GameSummary, Piece, Score,
+};
+
+/// Bounds of the rise interval that can be chosen in the menu, in seconds.
+pub const RISE_INTERVAL_RANGE: std::ops::RangeInclusive<f32> = 0.5..=10.0;
+
+/// Garbage never rises faster than this, however long the game goes.
+const MIN_RISE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Where the holes of the rising rows are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SurvivalHoles {
+    /// One column for the whole game.
+    Clean,
+    /// The hole moves every now and then.
+    #[default]
+    Messy,
+    /// The hole moves with every row.
+    Cheese,
+}
+
+impl SurvivalHoles {
+    pub const ALL: [SurvivalHoles; 3] = [
+        SurvivalHoles::Clean,
+        SurvivalHoles::Messy,
+        SurvivalHoles::Cheese,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SurvivalHoles::Clean => "Clean holes",
+            SurvivalHoles::Messy => "Messy holes",
+            SurvivalHoles::Cheese => "Cheese holes",
+        }
+    }
+
+    fn pattern(self, rng: &mut impl RngCore) -> GarbagePattern {
+        match self {
+            SurvivalHoles::Clean => GarbagePattern::Clean {
+                hole_column: rng.next_u32() as usize,
+            },
+            SurvivalHoles::Messy => GarbagePattern::Messy { change_chance: 0.3 },
+            SurvivalHoles::Cheese => GarbagePattern::Cheese,
+        }
+    }
+}
+
+/// How the rise interval shrinks the longer the game goes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RiseCurve {
+    /// Garbage keeps rising at the starting rate.
+    Steady,
+    /// The rate grows by the starting rate every minute.
+    #[default]
+    Linear,
+    /// The rate doubles every minute.
+    Exponential,
+}
+
+impl RiseCurve {
+    pub const ALL: [RiseCurve; 3] = [RiseCurve::Steady, RiseCurve::Linear, RiseCurve::Exponential];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RiseCurve::Steady => "Steady",
+            RiseCurve::Linear => "Linear",
+            RiseCurve::Exponential => "Exponential",
+        }
+    }
+
+    /// Time between two rows after `elapsed` with the interval starting at
+    /// `start`.
+    fn interval(self, start: Duration, elapsed: Duration) -> Duration {
+        let minutes = elapsed.as_secs_f32() / 60.0;
+        let interval = match self {
+            RiseCurve::Steady => start,
+            RiseCurve::Linear => start.div_f32(1.0 + minutes),
+            RiseCurve::Exponential => start.div_f32(2f32.powf(minutes)),
+        };
+        interval.max(MIN_RISE_INTERVAL)
+    }
+}
+
+/// How the next survival game is played, chosen in the menu.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct SurvivalSettings {
+    /// Seconds between two rows at the start.
+    pub rise_interval: f32,
+    pub holes: SurvivalHoles,
+    pub curve: RiseCurve,
+}
+
+impl Default for SurvivalSettings {
+    fn default() -> Self {
+        Self {
+            rise_interval: 5.0,
+            holes: default(),
+            curve: default(),
+        }
+    }
+}
+
+/// A board with garbage rising under it, faster and faster along its
+/// [`RiseCurve`] until it tops out.
+#[derive(Component, Debug)]
+pub struct Survival {
+    start_interval: Duration,
+    curve: RiseCurve,
+    pattern: GarbagePattern,
+    since_rise: Duration,
+    /// Rows whose time came while a piece was falling, they rise as soon as
+    /// it locks.
+    pending: u32,
+    risen: u32,
+    /// Hole of the last row, so the pattern carries on over single rows.
+    hole_column: Option<usize>,
+}
+
+impl Survival {
+    pub fn new(settings: SurvivalSettings, rng: &mut impl RngCore) -> Self {
+        Self {
+            start_interval: Duration::from_secs_f32(settings.rise_interval),
+            curve: settings.curve,
+            pattern: settings.holes.pattern(rng),
+            since_rise: Duration::ZERO,
+            pending: 0,
+            risen: 0,
+            hole_column: None,
+        }
+    }
+
+    fn interval(&self, elapsed: Duration) -> Duration {
+        self.curve.interval(self.start_interval, elapsed)
+    }
+}
+
+/// Counts down to the next row with the interval of the rise curve at the
+/// current time. Rows wait while a piece is falling and then rise together,
+/// and a row that pushes blocks out of the top ends the game.
+pub(super) fn raise_survival_garbage(
+    mut commands: Commands,
+    time: Res<Time>,
+    clock: Res<GameClock>,
+    piece_query: Query<&Parent, With<Piece>>,
+    mut survival_query: Query<
+        (
+            Entity,
+            &mut Playfield,
+            &mut Survival,
+            &mut EntropyComponent<ChaCha8Rng>,
+        ),
+        Without<ToppedOut>,
+    >,
+) {
+    for (board, mut playfield, mut survival, mut rng) in survival_query.iter_mut() {
+        survival.since_rise += time.delta();
+        loop {
+            let interval = survival.interval(clock.elapsed);
+            if survival.since_rise < interval {
+                break;
+            }
+            survival.since_rise -= interval;
+            survival.pending += 1;
+        }
+        if survival.pending == 0 || piece_query.iter().any(|parent| parent.get() == board) {
+            continue;
+        }
+
+        let pattern = survival.pattern;
+        let width = playfield.size().x as usize;
+        let mut topped_out = false;
+        for _ in 0..survival.pending {
+            let hole_column = pattern.next_hole(survival.hole_column, width, &mut *rng);
+            topped_out |= playfield.push_garbage_row(hole_column);
+            survival.hole_column = Some(hole_column);
+        }
+        survival.risen += survival.pending;
+        survival.pending = 0;
+
+        if topped_out {
+            commands.entity(board).insert(ToppedOut);
+        }
+    }
+}
+
+pub(super) fn survival_hud(
+    mut contexts: EguiContexts,
+    clock: Res<GameClock>,
+    survival_query: Query<&Survival>,
+) {
+    for survival in survival_query.iter() {
+        egui::Window::new("Survival").show(contexts.ctx_mut(), |ui| {
+            ui.heading(format_time(clock.elapsed));
+            ui.label(format!(
+                "Rows per minute: {:.0}",
+                60.0 / survival.interval(clock.elapsed).as_secs_f32()
+            ));
+            ui.label(format!("Rows risen: {}", survival.risen));
+        });
+    }
+}
+
+/// Records how long the player held out against the rising rows.
+pub(super) fn summarize_survival(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    survival_query: Query<(&Score, &Survival)>,
+) {
+    for (score, survival) in survival_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: format!("Survived {}", format_time(clock.elapsed)),
+            rows: vec![
+                ("Rows risen", survival.risen.to_string()),
+                ("Garbage lines", score.garbage_lines.to_string()),
+                ("Lines", score.lines.to_string()),
+                ("Pieces", score.pieces.to_string()),
+            ],
+        });
+    }
+}
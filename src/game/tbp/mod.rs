@@ -0,0 +1,491 @@
+pub mod protocol;
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::{HashSet, VecDeque},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{channel, Receiver},
+        Mutex,
+    },
+    thread,
+};
+
+use bevy::prelude::*;
+
+use crate::setup::GameState;
+
+use self::protocol::{
+    BotMessage, FrontendMessage, Move, PieceLocation, Spin, Start, TbpPiece, BOARD_HEIGHT,
+    BOARD_WIDTH,
+};
+
+use super::{
+    board::{Board, Controls},
+    lock_piece, master_gravity, move_piece,
+    piece_order::PieceOrder,
+    piece_types::{iter_cells, iter_piece_cells, piece_letter, PieceType},
+    playfield::{Cell, CheckRotationResult, Playfield},
+    rotation::Rotation,
+    Piece, PieceLocked, Score,
+};
+
+/// Seconds between two placements of the bot, so a game can be followed on screen.
+const BOT_MOVE_DELAY: f32 = 0.2;
+
+/// Lets an external bot play the game over the Tetris Bot Protocol.
+///
+/// The bot executable is launched at startup and talks JSON lines over its
+/// stdin and stdout.
+pub struct TbpPlugin {
+    pub bot_path: PathBuf,
+}
+
+impl Plugin for TbpPlugin {
+    fn name(&self) -> &str {
+        "tbp_frontend"
+    }
+
+    fn build(&self, app: &mut App) {
+        let bot = match TbpBot::launch(&self.bot_path) {
+            Ok(bot) => bot,
+            Err(e) => {
+                error!(
+                    "Could not launch bot {}, playing without it: {e}",
+                    self.bot_path.display()
+                );
+                return;
+            }
+        };
+        app.insert_resource(bot)
+            .add_systems(
+                Update,
+                (
+                    receive_bot_messages,
+                    (claim_board, request_suggestion, apply_suggestion)
+                        .run_if(in_state(GameState::InGame)),
+                )
+                    .chain()
+                    .after(move_piece)
+                    .after(master_gravity),
+            )
+            .add_systems(OnExit(GameState::InGame), stop_bot);
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum BotState {
+    /// Waiting for the `info` message the bot sends on startup.
+    Launching,
+    /// `rules` were sent, waiting for `ready`.
+    AwaitingReady,
+    Ready,
+    /// The bot rejected the rules or the connection broke.
+    Failed,
+}
+
+#[derive(Resource)]
+pub(super) struct TbpBot {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Mutex<Receiver<BotMessage>>,
+    state: BotState,
+    /// Whether `start` was sent for the current game.
+    started: bool,
+    /// Number of pieces the bot currently has in its queue.
+    queue_len: usize,
+    /// The piece a suggestion was requested for.
+    requested_piece: Option<Entity>,
+    /// The suggestion for the requested piece, once it arrived.
+    suggestion: Option<Vec<Move>>,
+    waiting_for_suggestion: bool,
+    move_timer: Timer,
+}
+
+impl TbpBot {
+    fn launch(bot_path: &Path) -> std::io::Result<Self> {
+        let mut child = Command::new(bot_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                match serde_json::from_str::<BotMessage>(&line) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => warn!("Ignoring bot message {line:?}: {e}"),
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            messages: Mutex::new(receiver),
+            state: BotState::Launching,
+            started: false,
+            queue_len: 0,
+            requested_piece: None,
+            suggestion: None,
+            waiting_for_suggestion: false,
+            move_timer: Timer::from_seconds(BOT_MOVE_DELAY, TimerMode::Once),
+        })
+    }
+
+    fn send(&mut self, message: &FrontendMessage) {
+        let result = serde_json::to_writer(&mut self.stdin, message)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(self.stdin))
+            .and_then(|_| self.stdin.flush());
+
+        if let Err(e) = result {
+            error!("Could not send message to bot: {e}");
+            self.state = BotState::Failed;
+        }
+    }
+
+    fn reset_game(&mut self) {
+        self.started = false;
+        self.queue_len = 0;
+        self.requested_piece = None;
+        self.suggestion = None;
+        self.waiting_for_suggestion = false;
+    }
+}
+
+/// Marks the board the bot plays on.
+#[derive(Component)]
+pub(super) struct TbpControlled;
+
+impl Drop for TbpBot {
+    fn drop(&mut self) {
+        self.send(&FrontendMessage::Quit);
+        let _ = self.child.kill();
+    }
+}
+
+impl From<PieceType> for TbpPiece {
+    fn from(value: PieceType) -> Self {
+        match value {
+            PieceType::O => TbpPiece::O,
+            PieceType::J => TbpPiece::J,
+            PieceType::L => TbpPiece::L,
+            PieceType::S => TbpPiece::S,
+            PieceType::T => TbpPiece::T,
+            PieceType::Z => TbpPiece::Z,
+            PieceType::I => TbpPiece::I,
+        }
+    }
+}
+
+fn board_from_playfield(playfield: &Playfield) -> protocol::Board {
+    (0..BOARD_HEIGHT as i32)
+        .map(|y| {
+            (0..BOARD_WIDTH as i32)
+                .map(|x| match playfield.get(IVec2::new(x, y)) {
+                    Some(Cell::Filled(piece_type) | Cell::Item(piece_type, _)) => {
+                        Some(piece_letter(*piece_type))
+                    }
+                    Some(Cell::Garbage) => Some('G'),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Finds the rotation and position of our rotation system that covers the
+/// same cells as the TBP location.
+fn piece_from_location(piece_type: PieceType, location: &PieceLocation) -> Option<Piece> {
+    let mut target = location.cells().map(|(x, y)| IVec2::new(x, y));
+    target.sort_by_key(|c| (c.y, c.x));
+
+    [Rotation::R0, Rotation::R90, Rotation::R180, Rotation::R270]
+        .into_iter()
+        .find_map(|rotation| {
+            let mut cells: Vec<_> = iter_cells(piece_type, rotation).collect();
+            cells.sort_by_key(|c| (c.y, c.x));
+
+            let position = target[0] - cells[0];
+            cells
+                .iter()
+                .zip(target.iter())
+                .all(|(cell, target)| *cell + position == *target)
+                .then_some(Piece {
+                    position,
+                    rotation,
+                    piece_type,
+                    rotated_last: false,
+                    scale: 1,
+                    item: None,
+                    mirrored: false,
+                })
+        })
+}
+
+/// Whether `piece` can be moved to `target` and locked there: the target has
+/// to rest on the stack or the floor and be reachable by shifting, soft
+/// dropping and rotating, the way a player could move the piece.
+fn is_valid_placement(playfield: &Playfield, piece: &Piece, target: &Piece) -> bool {
+    let resting = !playfield.check_move(&Piece {
+        position: target.position + IVec2::NEG_Y,
+        ..*target
+    });
+    if !playfield.check_move(target) || !resting {
+        return false;
+    }
+
+    // pieces that cover the same cells are the same placement, whatever
+    // their rotation
+    let cells = |piece: &Piece| {
+        let mut cells: Vec<_> = iter_piece_cells(piece).collect();
+        cells.sort_by_key(|c| (c.y, c.x));
+        cells
+    };
+    let target_cells = cells(target);
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([*piece]);
+    while let Some(current) = queue.pop_front() {
+        if !seen.insert((current.position, current.rotation)) {
+            continue;
+        }
+        if cells(&current) == target_cells {
+            return true;
+        }
+
+        for step in [IVec2::NEG_X, IVec2::X, IVec2::NEG_Y] {
+            let moved = Piece {
+                position: current.position + step,
+                ..current
+            };
+            if playfield.check_move(&moved) {
+                queue.push_back(moved);
+            }
+        }
+        for rotation in [current.rotation.ccw(), current.rotation.cw()] {
+            let rotated = Piece {
+                rotation,
+                ..current
+            };
+            if let CheckRotationResult::ValidWithOffset(offset) = playfield.check_rotation(&rotated)
+            {
+                queue.push_back(Piece {
+                    position: rotated.position + offset,
+                    ..rotated
+                });
+            }
+        }
+    }
+    false
+}
+
+fn receive_bot_messages(mut bot: ResMut<TbpBot>) {
+    let messages: Vec<_> = bot
+        .messages
+        .lock()
+        .expect("bot channel poisoned")
+        .try_iter()
+        .collect();
+
+    for message in messages {
+        match message {
+            BotMessage::Info {
+                name,
+                version,
+                author,
+                ..
+            } if bot.state == BotState::Launching => {
+                info!("Bot {name} {version} by {author} connected");
+                bot.send(&FrontendMessage::Rules);
+                bot.state = BotState::AwaitingReady;
+            }
+            BotMessage::Ready if bot.state == BotState::AwaitingReady => {
+                bot.state = BotState::Ready;
+            }
+            BotMessage::Error { reason } => {
+                error!("Bot reported an error: {reason}");
+                bot.state = BotState::Failed;
+            }
+            BotMessage::Suggestion { moves } if bot.waiting_for_suggestion => {
+                bot.waiting_for_suggestion = false;
+                bot.suggestion = Some(moves);
+            }
+            message => warn!("Unexpected bot message {message:?}"),
+        }
+    }
+}
+
+/// Hands the first board of a game to the bot instead of the keyboard.
+fn claim_board(
+    mut commands: Commands,
+    mut new_board_query: Query<(Entity, &Board, &mut Playfield), Added<Board>>,
+    controlled_query: Query<(), With<TbpControlled>>,
+) {
+    if !controlled_query.is_empty() {
+        return;
+    }
+    if let Some((board, _, mut playfield)) = new_board_query
+        .iter_mut()
+        .min_by_key(|(_, board, _)| board.index)
+    {
+        // the bot places pieces by their cells in its own coordinates
+        playfield.set_mirrored(false);
+        commands
+            .entity(board)
+            .remove::<Controls>()
+            .insert(TbpControlled);
+    }
+}
+
+fn request_suggestion(
+    mut bot: ResMut<TbpBot>,
+    mut locked_events: EventReader<PieceLocked>,
+    piece_query: Query<(Entity, &Piece, &Parent)>,
+    board_query: Query<(Entity, &Playfield, &PieceOrder), With<TbpControlled>>,
+) {
+    let locked_boards: Vec<_> = locked_events.read().map(|locked| locked.board).collect();
+    if bot.state != BotState::Ready {
+        return;
+    }
+    let Ok((board, playfield, piece_order)) = board_query.get_single() else {
+        return;
+    };
+    if bot.requested_piece.is_some() && locked_boards.contains(&board) {
+        // Gravity locked the piece this frame, before the bot's move was
+        // applied. It is only despawned at the end of the frame, so it must
+        // not be locked a second time.
+        bot.send(&FrontendMessage::Stop);
+        bot.reset_game();
+        return;
+    }
+    let Some((entity, piece, _)) = piece_query
+        .iter()
+        .find(|(_, _, parent)| parent.get() == board)
+    else {
+        return;
+    };
+
+    if bot.requested_piece == Some(entity) {
+        return;
+    }
+    if bot.requested_piece.is_some() {
+        // Gravity locked the piece before the bot's move was applied, so the
+        // boards are out of sync and the bot has to start over.
+        bot.send(&FrontendMessage::Stop);
+        bot.reset_game();
+    }
+
+    let queue: Vec<TbpPiece> = std::iter::once(piece.piece_type)
+        .chain(piece_order.upcoming().iter().copied())
+        .map(TbpPiece::from)
+        .collect();
+
+    if !bot.started {
+        bot.send(&FrontendMessage::Start(Start {
+            hold: None,
+            queue: queue.clone(),
+            combo: 0,
+            back_to_back: false,
+            board: board_from_playfield(playfield),
+        }));
+        bot.started = true;
+        bot.queue_len = queue.len();
+    } else {
+        for &piece in &queue[bot.queue_len.min(queue.len())..] {
+            bot.send(&FrontendMessage::NewPiece { piece });
+        }
+        bot.queue_len = bot.queue_len.max(queue.len());
+    }
+
+    bot.send(&FrontendMessage::Suggest);
+    bot.requested_piece = Some(entity);
+    bot.waiting_for_suggestion = true;
+    bot.move_timer.reset();
+}
+
+fn apply_suggestion(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut bot: ResMut<TbpBot>,
+    mut locked_events: EventWriter<PieceLocked>,
+    mut piece_query: Query<&mut Piece>,
+    mut board_query: Query<(Entity, &mut Playfield, &mut Score), With<TbpControlled>>,
+) {
+    if !bot.move_timer.tick(time.delta()).finished() {
+        return;
+    }
+    let Some(entity) = bot.requested_piece else {
+        return;
+    };
+    let Ok(mut piece) = piece_query.get_mut(entity) else {
+        return;
+    };
+    let Some(moves) = bot.suggestion.take() else {
+        return;
+    };
+    let Ok((board, mut playfield, mut score)) = board_query.get_single_mut() else {
+        return;
+    };
+
+    let suggested = moves.iter().find_map(|mv| {
+        (mv.location.piece == TbpPiece::from(piece.piece_type))
+            .then(|| piece_from_location(piece.piece_type, &mv.location))
+            .flatten()
+            .filter(|target| is_valid_placement(&playfield, &piece, target))
+            .map(|target| (*mv, target))
+    });
+
+    if let Some((mv, target)) = suggested {
+        *piece = Piece {
+            rotated_last: mv.spin != Spin::None,
+            ..target
+        };
+        bot.send(&FrontendMessage::Play { mv });
+        bot.queue_len = bot.queue_len.saturating_sub(1);
+        bot.requested_piece = None;
+    } else {
+        // The bot wants something we can't do, so we drop the piece where it
+        // is and start over with the resulting board.
+        warn!("Bot suggested no valid move for {:?}", piece.piece_type);
+        while playfield.check_move(&Piece {
+            position: piece.position + IVec2::NEG_Y,
+            ..*piece
+        }) {
+            piece.position += IVec2::NEG_Y;
+        }
+        bot.send(&FrontendMessage::Stop);
+        bot.reset_game();
+    }
+
+    lock_piece(
+        &mut commands,
+        &mut locked_events,
+        board,
+        entity,
+        &piece,
+        &mut playfield,
+        &mut score,
+    );
+}
+
+fn stop_bot(mut bot: ResMut<TbpBot>) {
+    if bot.started {
+        bot.send(&FrontendMessage::Stop);
+    }
+    bot.reset_game();
+}
@@ -0,0 +1,135 @@
+//! Messages of the Tetris Bot Protocol (TBP).
+//!
+//! This file is shared with the reference bot in `src/bin/tbp_bot.rs`,
+//! so it must only depend on serde.
+
+use serde::{Deserialize, Serialize};
+
+/// Number of rows of a TBP board. Rows above our playfield are sent empty.
+pub const BOARD_HEIGHT: usize = 40;
+
+/// Number of columns of a TBP board.
+pub const BOARD_WIDTH: usize = 10;
+
+/// A board is a list of rows from the bottom up. Each cell is either empty or
+/// holds the letter of the piece it came from (`G` for garbage).
+pub type Board = Vec<Vec<Option<char>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TbpPiece {
+    I,
+    O,
+    T,
+    L,
+    J,
+    S,
+    Z,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceLocation {
+    #[serde(rename = "type")]
+    pub piece: TbpPiece,
+    pub orientation: Orientation,
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    pub location: PieceLocation,
+    pub spin: Spin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Start {
+    pub hold: Option<TbpPiece>,
+    pub queue: Vec<TbpPiece>,
+    pub combo: u32,
+    pub back_to_back: bool,
+    pub board: Board,
+}
+
+/// Messages sent from the game to the bot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    Rules,
+    Start(Start),
+    Stop,
+    Suggest,
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    NewPiece {
+        piece: TbpPiece,
+    },
+    Quit,
+}
+
+/// Messages sent from the bot to the game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    Error {
+        reason: String,
+    },
+    Ready,
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        features: Vec<String>,
+    },
+    Suggestion {
+        moves: Vec<Move>,
+    },
+}
+
+impl TbpPiece {
+    /// Mino offsets in north orientation relative to the piece center.
+    fn north_cells(self) -> [(i32, i32); 4] {
+        match self {
+            TbpPiece::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            TbpPiece::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            TbpPiece::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            TbpPiece::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            TbpPiece::J => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+            TbpPiece::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            TbpPiece::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+        }
+    }
+}
+
+impl PieceLocation {
+    /// Absolute board coordinates of the four minos of this location.
+    pub fn cells(&self) -> [(i32, i32); 4] {
+        self.piece.north_cells().map(|(x, y)| {
+            let (x, y) = match self.orientation {
+                Orientation::North => (x, y),
+                Orientation::East => (y, -x),
+                Orientation::South => (-x, -y),
+                Orientation::West => (-y, x),
+            };
+            (self.x + x, self.y + y)
+        })
+    }
+}
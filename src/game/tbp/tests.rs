@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::game::{
+    piece_types::{iter_piece_cells, PieceType},
+    playfield::{Cell, Playfield},
+    rotation::Rotation,
+    Piece,
+};
+
+use super::{
+    is_valid_placement, piece_from_location,
+    protocol::{Orientation, PieceLocation, TbpPiece},
+    TbpBot,
+};
+
+const PIECE_TYPES: [PieceType; 7] = [
+    PieceType::O,
+    PieceType::J,
+    PieceType::L,
+    PieceType::S,
+    PieceType::T,
+    PieceType::Z,
+    PieceType::I,
+];
+
+#[test]
+fn missing_bot_is_an_error() {
+    assert!(TbpBot::launch(Path::new("does/not/exist/tbp_bot")).is_err());
+}
+
+/// Every TBP location maps to a piece of ours that covers the same cells.
+#[test]
+fn locations_map_to_pieces() {
+    let orientations = [
+        Orientation::North,
+        Orientation::East,
+        Orientation::South,
+        Orientation::West,
+    ];
+    for piece_type in PIECE_TYPES {
+        for orientation in orientations {
+            let location = PieceLocation {
+                piece: TbpPiece::from(piece_type),
+                orientation,
+                x: 4,
+                y: 10,
+            };
+            let piece = piece_from_location(piece_type, &location)
+                .unwrap_or_else(|| panic!("no piece for {location:?}"));
+
+            let mut expected: Vec<_> = location
+                .cells()
+                .iter()
+                .map(|&(x, y)| IVec2::new(x, y))
+                .collect();
+            let mut cells: Vec<_> = iter_piece_cells(&piece).collect();
+            expected.sort_by_key(|c| (c.y, c.x));
+            cells.sort_by_key(|c| (c.y, c.x));
+            assert_eq!(cells, expected, "{location:?}");
+        }
+    }
+}
+
+fn t_piece(x: i32, y: i32, rotation: Rotation) -> Piece {
+    Piece {
+        position: IVec2::new(x, y),
+        rotation,
+        ..Piece::new(PieceType::T)
+    }
+}
+
+#[test]
+fn placements_must_rest_on_something() {
+    let playfield = Playfield::new(UVec2::new(10, 24));
+    let piece = Piece::new(PieceType::T);
+
+    assert!(is_valid_placement(
+        &playfield,
+        &piece,
+        &t_piece(2, 0, Rotation::R0)
+    ));
+    assert!(!is_valid_placement(
+        &playfield,
+        &piece,
+        &t_piece(2, 5, Rotation::R0)
+    ));
+}
+
+#[test]
+fn placements_must_be_reachable() {
+    let mut playfield = Playfield::new(UVec2::new(10, 24));
+    // a roof over the bottom two rows
+    for x in 0..10 {
+        playfield.set_cell(IVec2::new(x, 2), Cell::Garbage);
+    }
+    let piece = Piece::new(PieceType::T);
+
+    // it fits under the roof, but there is no way in
+    let sealed = t_piece(2, 0, Rotation::R0);
+    assert!(playfield.check_move(&sealed));
+    assert!(!is_valid_placement(&playfield, &piece, &sealed));
+
+    assert!(is_valid_placement(
+        &playfield,
+        &piece,
+        &t_piece(3, 3, Rotation::R0)
+    ));
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::setup::GameState;
+
+use super::{
+    clock::{format_time, GameClock},
+    GameSummary, Score,
+};
+
+/// Length of an ultra game.
+pub const ULTRA_TIME: Duration = Duration::from_secs(120);
+
+/// A board that scores as much as possible until the clock runs out.
+#[derive(Component, Debug)]
+pub struct Ultra;
+
+/// Ends the game as soon as the time is up, even while a piece is falling.
+pub(super) fn finish_ultra(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    ultra_query: Query<&Score, With<Ultra>>,
+) {
+    if !clock.is_up() {
+        return;
+    }
+    for score in ultra_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: "Time's up!".to_string(),
+            rows: vec![
+                ("Score", score.score.to_string()),
+                ("Lines", score.lines.to_string()),
+                (
+                    "Pieces per second",
+                    format!("{:.2}", score.pieces as f32 / clock.elapsed.as_secs_f32()),
+                ),
+            ],
+        });
+        commands.insert_resource(NextState(Some(GameState::Finished)));
+    }
+}
+
+pub(super) fn ultra_hud(
+    mut contexts: EguiContexts,
+    clock: Res<GameClock>,
+    ultra_query: Query<&Score, With<Ultra>>,
+) {
+    let Some(remaining) = clock.remaining() else {
+        return;
+    };
+    for score in ultra_query.iter() {
+        egui::Window::new("Ultra").show(contexts.ctx_mut(), |ui| {
+            ui.heading(format_time(remaining));
+            ui.label(format!("Score: {}", score.score));
+        });
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+use bevy_prng::ChaCha8Rng;
+use rand_core::RngCore;
+
+use super::{
+    board::ToppedOut,
+    playfield::{GarbagePattern, Playfield, TSpin},
+    PieceLocked,
+};
+
+/// The board that receives the garbage this board sends.
+#[derive(Component, Debug)]
+pub struct Opponent(pub Entity);
+
+/// Garbage rows that wait to be inserted below the stack of this board.
+#[derive(Component, Debug)]
+pub struct PendingGarbage {
+    pub rows: u32,
+    /// Garbage has its own generator, so inserting it does not change the
+    /// pieces of the board.
+    rng: ChaCha8Rng,
+}
+
+impl PendingGarbage {
+    pub fn new(rng: ChaCha8Rng) -> Self {
+        Self { rows: 0, rng }
+    }
+}
+
+/// Makes the two boards send their garbage to each other. Both get their
+/// holes from the same sequence.
+pub fn link_opponents(commands: &mut Commands, a: Entity, b: Entity, rng: ChaCha8Rng) {
+    commands
+        .entity(a)
+        .insert((Opponent(b), PendingGarbage::new(rng.clone())));
+    commands
+        .entity(b)
+        .insert((Opponent(a), PendingGarbage::new(rng)));
+}
+
+/// Sent for every lock of a board that takes part in garbage exchange.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GarbageExchanged {
+    pub locked: PieceLocked,
+    /// Rows sent on after cancelling the own pending garbage.
+    pub sent: u32,
+    /// Number of rows and hole column of garbage inserted into the board.
+    pub inserted: Option<(usize, usize)>,
+}
+
+/// Extra garbage for consecutive clears, indexed by the combo count.
+const COMBO_ATTACK: [u32; 12] = [0, 0, 1, 1, 1, 2, 2, 3, 3, 4, 4, 4];
+
+/// Number of garbage rows a lock sends according to the usual guideline
+/// attack table.
+fn attack(locked: &PieceLocked) -> u32 {
+    if locked.cleared_rows == 0 {
+        return 0;
+    }
+
+    let base = match (locked.t_spin, locked.cleared_rows) {
+        (TSpin::None, 1) => 0,
+        (TSpin::None, 2) => 1,
+        (TSpin::None, 3) => 2,
+        (TSpin::None, _) => 4,
+        (TSpin::Mini, 1) => 0,
+        (TSpin::Mini, _) => 1,
+        (TSpin::Full, rows) => 2 * rows.min(3) as u32,
+    };
+    let combo_index = (locked.combo as usize - 1).min(COMBO_ATTACK.len() - 1);
+    let back_to_back = u32::from(locked.back_to_back);
+    let perfect_clear = if locked.perfect_clear { 10 } else { 0 };
+
+    base + COMBO_ATTACK[combo_index] + back_to_back + perfect_clear
+}
+
+/// Clearing lines first cancels garbage waiting for the own board and sends
+/// the rest to the opponent. Pending garbage is inserted when a piece locks
+/// without clearing anything.
+pub(super) fn exchange_garbage(
+    mut commands: Commands,
+    mut locked_events: EventReader<PieceLocked>,
+    mut exchanged_events: EventWriter<GarbageExchanged>,
+    mut board_query: Query<(&mut Playfield, &mut PendingGarbage, Option<&Opponent>)>,
+) {
+    for locked in locked_events.read() {
+        let Ok((mut playfield, mut pending, opponent)) = board_query.get_mut(locked.board) else {
+            continue;
+        };
+
+        if locked.cleared_rows == 0 {
+            let mut inserted = None;
+            if pending.rows > 0 {
+                // every attack arrives as one clean block of garbage
+                let rows = pending.rows as usize;
+                let hole_column = pending.rng.next_u32() as usize;
+                let pattern = GarbagePattern::Clean { hole_column };
+                if playfield.add_garbage(rows, pattern, &mut pending.rng) {
+                    commands.entity(locked.board).insert(ToppedOut);
+                }
+                pending.rows = 0;
+                inserted = Some((rows, hole_column));
+            }
+            exchanged_events.send(GarbageExchanged {
+                locked: *locked,
+                sent: 0,
+                inserted,
+            });
+            continue;
+        }
+
+        let mut lines = attack(locked);
+        let cancelled = lines.min(pending.rows);
+        pending.rows -= cancelled;
+        lines -= cancelled;
+        exchanged_events.send(GarbageExchanged {
+            locked: *locked,
+            sent: lines,
+            inserted: None,
+        });
+
+        let Some(&Opponent(opponent)) = opponent else {
+            continue;
+        };
+        if let Ok((_, mut opponent_pending, _)) = board_query.get_mut(opponent) {
+            opponent_pending.rows += lines;
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::setup::GameState;
+
+use super::{
+    clock::{format_time, GameClock},
+    GameSummary, Score,
+};
+
+/// Rows emptied at the top when a zen board would top out.
+pub const ZEN_CLEARED_ROWS: usize = 10;
+
+/// How pieces fall in zen mode, chosen in the menu.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZenGravity {
+    /// Gravity stays at the speed of level 1.
+    #[default]
+    Fixed,
+    /// Pieces only move and lock when the player says so.
+    Off,
+}
+
+impl ZenGravity {
+    pub const ALL: [ZenGravity; 2] = [ZenGravity::Fixed, ZenGravity::Off];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ZenGravity::Fixed => "Fixed gravity",
+            ZenGravity::Off => "No gravity",
+        }
+    }
+}
+
+/// A board that never tops out. It plays until the player finishes it.
+#[derive(Component, Debug)]
+pub struct Zen;
+
+pub(super) fn zen_hud(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    zen_query: Query<&Score, With<Zen>>,
+) {
+    for score in zen_query.iter() {
+        egui::Window::new("Zen").show(contexts.ctx_mut(), |ui| {
+            ui.label(format_time(clock.elapsed));
+            ui.label(format!("Pieces: {}", score.pieces));
+            if ui.button("Finish").clicked() {
+                commands.insert_resource(NextState(Some(GameState::Finished)));
+            }
+        });
+    }
+}
+
+pub(super) fn summarize_zen(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    zen_query: Query<&Score, With<Zen>>,
+) {
+    for score in zen_query.iter() {
+        commands.insert_resource(GameSummary {
+            heading: "Session finished".to_string(),
+            rows: vec![
+                ("Lines", score.lines.to_string()),
+                ("Pieces", score.pieces.to_string()),
+                ("Time", format_time(clock.elapsed)),
+            ],
+        });
+    }
+}
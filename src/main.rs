@@ -1,19 +1,37 @@
 mod game;
 mod setup;
 
+use std::path::PathBuf;
+
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins,
-            EguiPlugin,
-            setup::SetupPlugin,
-            game::GamePlugin,
-        ))
-        .add_systems(Update, bevy::window::close_on_esc)
-        .add_plugins(WorldInspectorPlugin::new())
-        .run();
+    let mut app = App::new();
+
+    app.add_plugins((
+        DefaultPlugins,
+        EguiPlugin,
+        setup::SetupPlugin,
+        game::GamePlugin,
+    ))
+    .add_systems(Update, bevy::window::close_on_esc)
+    .add_plugins(WorldInspectorPlugin::new());
+
+    if let Some(bot_path) = arg_value("--bot") {
+        app.add_plugins(game::TbpPlugin {
+            bot_path: PathBuf::from(bot_path),
+        });
+    }
+    if let Some(address) = arg_value("--spectate") {
+        app.add_plugins(game::SpectatorPlugin { address });
+    }
+
+    app.run();
+}
+
+/// Reads the value of an option like `--bot <path>`.
+fn arg_value(option: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != option).nth(1)
 }
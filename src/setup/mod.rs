@@ -1,12 +1,15 @@
-use bevy::{asset::LoadState, prelude::*};
+use bevy::prelude::*;
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
 pub enum GameState {
     #[default]
     Loading,
+    Menu,
     SetupGame,
     InGame,
     GameOver,
+    /// The goal of the mode was reached.
+    Finished,
 }
 
 pub struct SetupPlugin;
@@ -30,7 +33,6 @@ impl Plugin for SetupPlugin {
 pub struct CellTextures {
     pub atlas: Handle<TextureAtlas>,
     pub size: f32,
-    pub count: usize,
 }
 
 fn setup_camera(mut commands: Commands) {
@@ -52,7 +54,6 @@ fn load_sprites(
     commands.insert_resource(CellTextures {
         atlas: texture_atlas_handle,
         size: tile_size.x,
-        count: 16,
     });
 }
 
@@ -65,7 +66,7 @@ fn wait_for_loading(
     for &event in texture_atlas_events.read() {
         if let AssetEvent::Added { id } = event {
             if id == atlas_id {
-                commands.insert_resource(NextState(Some(GameState::SetupGame)));
+                commands.insert_resource(NextState(Some(GameState::Menu)));
             }
         } else if let AssetEvent::Removed { id: _ } = event {
             panic!("Could not load sprites")
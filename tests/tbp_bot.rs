@@ -0,0 +1,111 @@
+//! Starts the reference bot and plays a few pieces with it over the Tetris
+//! Bot Protocol, without the game.
+
+#[path = "../src/game/tbp/protocol.rs"]
+mod protocol;
+
+use std::{
+    io::{BufRead, BufReader, Lines, Write},
+    process::{ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use protocol::{
+    BotMessage, FrontendMessage, Move, Orientation, PieceLocation, Spin, Start, TbpPiece,
+    BOARD_HEIGHT, BOARD_WIDTH,
+};
+use serde_json::Value;
+
+fn send(stdin: &mut ChildStdin, message: &FrontendMessage) {
+    serde_json::to_writer(&mut *stdin, message).unwrap();
+    writeln!(stdin).unwrap();
+    stdin.flush().unwrap();
+}
+
+fn receive(stdout: &mut Lines<BufReader<ChildStdout>>) -> BotMessage {
+    let line = stdout.next().expect("the bot answers").unwrap();
+    serde_json::from_str(&line).unwrap_or_else(|e| panic!("bad message {line:?}: {e}"))
+}
+
+/// A message reads back as the same JSON it was written as.
+fn assert_round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(message: &T) {
+    let json = serde_json::to_value(message).unwrap();
+    let read: T = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(read).unwrap(), json);
+}
+
+#[test]
+fn messages_round_trip() {
+    let location = PieceLocation {
+        piece: TbpPiece::T,
+        orientation: Orientation::South,
+        x: 4,
+        y: 1,
+    };
+    let mv = Move {
+        location,
+        spin: Spin::Full,
+    };
+    assert_round_trip(&FrontendMessage::Start(Start {
+        hold: Some(TbpPiece::I),
+        queue: vec![TbpPiece::T, TbpPiece::Z],
+        combo: 2,
+        back_to_back: true,
+        board: vec![vec![Some('G'), None]],
+    }));
+    assert_round_trip(&FrontendMessage::Play { mv });
+    assert_round_trip(&FrontendMessage::NewPiece { piece: TbpPiece::L });
+    assert_round_trip(&BotMessage::Suggestion { moves: vec![mv] });
+
+    // the field names the protocol expects
+    let json = serde_json::to_value(FrontendMessage::Play { mv }).unwrap();
+    assert_eq!(json["type"], Value::from("play"));
+    assert_eq!(json["move"]["location"]["type"], Value::from("T"));
+    assert_eq!(
+        json["move"]["location"]["orientation"],
+        Value::from("south")
+    );
+}
+
+#[test]
+fn reference_bot_plays() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tbp_bot"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("the bot starts");
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+
+    assert!(matches!(receive(&mut stdout), BotMessage::Info { .. }));
+    send(&mut stdin, &FrontendMessage::Rules);
+    assert!(matches!(receive(&mut stdout), BotMessage::Ready));
+
+    let queue = vec![TbpPiece::I, TbpPiece::O, TbpPiece::T];
+    send(
+        &mut stdin,
+        &FrontendMessage::Start(Start {
+            hold: None,
+            queue: queue.clone(),
+            combo: 0,
+            back_to_back: false,
+            board: vec![vec![None; BOARD_WIDTH]; BOARD_HEIGHT],
+        }),
+    );
+
+    for piece in queue {
+        send(&mut stdin, &FrontendMessage::Suggest);
+        let BotMessage::Suggestion { moves } = receive(&mut stdout) else {
+            panic!("expected a suggestion");
+        };
+        let mv = *moves.first().expect("an empty board has room");
+        assert_eq!(mv.location.piece, piece);
+        for (x, y) in mv.location.cells() {
+            assert!((0..BOARD_WIDTH as i32).contains(&x), "{mv:?}");
+            assert!((0..BOARD_HEIGHT as i32).contains(&y), "{mv:?}");
+        }
+        send(&mut stdin, &FrontendMessage::Play { mv });
+    }
+
+    send(&mut stdin, &FrontendMessage::Quit);
+    assert!(child.wait().unwrap().success());
+}